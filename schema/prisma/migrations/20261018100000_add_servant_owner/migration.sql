@@ -0,0 +1,8 @@
+-- AlterTable
+ALTER TABLE "servants" ADD COLUMN     "owner_id" UUID;
+
+-- CreateIndex
+CREATE INDEX "servants.owner_id_index" ON "servants"("owner_id");
+
+-- AddForeignKey
+ALTER TABLE "servants" ADD FOREIGN KEY ("owner_id") REFERENCES "identities"("id") ON DELETE CASCADE ON UPDATE CASCADE;
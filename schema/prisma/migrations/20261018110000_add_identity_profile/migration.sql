@@ -0,0 +1,4 @@
+-- AlterTable
+ALTER TABLE "identities" ADD COLUMN     "login" VARCHAR(255),
+ADD COLUMN     "name" VARCHAR(255),
+ADD COLUMN     "avatar_url" VARCHAR(2048);
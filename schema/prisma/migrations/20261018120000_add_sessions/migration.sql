@@ -0,0 +1,24 @@
+-- CreateTable
+CREATE TABLE "sessions" (
+    "id" UUID NOT NULL,
+    "session_key" VARCHAR(255) NOT NULL,
+    "identity_id" UUID,
+    "state" TEXT NOT NULL,
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "updated_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "expires_at" TIMESTAMPTZ(3) NOT NULL,
+
+    PRIMARY KEY ("id")
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "sessions.session_key_unique" ON "sessions"("session_key");
+
+-- CreateIndex
+CREATE INDEX "sessions.identity_id_index" ON "sessions"("identity_id");
+
+-- CreateIndex
+CREATE INDEX "sessions.expires_at_index" ON "sessions"("expires_at");
+
+-- AddForeignKey
+ALTER TABLE "sessions" ADD FOREIGN KEY ("identity_id") REFERENCES "identities"("id") ON DELETE CASCADE ON UPDATE CASCADE;
@@ -0,0 +1,3 @@
+-- Namespace existing identifiers with the provider they came from
+UPDATE "identities" SET "provider_identifier" = 'github:' || "provider_identifier"
+    WHERE "provider_identifier" NOT LIKE '%:%';
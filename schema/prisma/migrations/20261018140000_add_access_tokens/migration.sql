@@ -0,0 +1,22 @@
+-- CreateTable
+CREATE TABLE "access_tokens" (
+    "id" UUID NOT NULL,
+    "identity_id" UUID NOT NULL,
+    "name" VARCHAR(100) NOT NULL,
+    "token_hash" VARCHAR(64) NOT NULL,
+    "scopes" TEXT[],
+    "last_used_at" TIMESTAMPTZ(3),
+    "expires_at" TIMESTAMPTZ(3),
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY ("id")
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "access_tokens.token_hash_unique" ON "access_tokens"("token_hash");
+
+-- CreateIndex
+CREATE INDEX "access_tokens.identity_id_index" ON "access_tokens"("identity_id");
+
+-- AddForeignKey
+ALTER TABLE "access_tokens" ADD FOREIGN KEY ("identity_id") REFERENCES "identities"("id") ON DELETE CASCADE ON UPDATE CASCADE;
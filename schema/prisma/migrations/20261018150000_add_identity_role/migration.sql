@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "identities" ADD COLUMN     "role" VARCHAR(16) NOT NULL DEFAULT E'user';
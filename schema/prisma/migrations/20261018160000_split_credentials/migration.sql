@@ -0,0 +1,29 @@
+-- CreateTable
+CREATE TABLE "credentials" (
+    "id" UUID NOT NULL,
+    "identity_id" UUID NOT NULL,
+    "provider_identifier" VARCHAR(255) NOT NULL,
+    "login" VARCHAR(255),
+    "linked_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY ("id")
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "credentials.provider_identifier_unique" ON "credentials"("provider_identifier");
+
+-- CreateIndex
+CREATE INDEX "credentials.identity_id_index" ON "credentials"("identity_id");
+
+-- AddForeignKey
+ALTER TABLE "credentials" ADD FOREIGN KEY ("identity_id") REFERENCES "identities"("id") ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- MigrateData
+INSERT INTO "credentials" ("id", "identity_id", "provider_identifier", "login", "linked_at")
+    SELECT gen_random_uuid(), "id", "provider_identifier", "login", "registered_at" FROM "identities";
+
+-- DropIndex
+DROP INDEX "identities.provider_identifier_unique";
+
+-- AlterTable
+ALTER TABLE "identities" DROP COLUMN "provider_identifier";
@@ -0,0 +1,23 @@
+-- CreateTable
+CREATE TABLE "webauthn_credentials" (
+    "id" UUID NOT NULL,
+    "identity_id" UUID NOT NULL,
+    "credential_id" VARCHAR(1366) NOT NULL,
+    "public_key" BYTEA NOT NULL,
+    "algorithm" INTEGER NOT NULL,
+    "sign_count" BIGINT NOT NULL DEFAULT 0,
+    "name" VARCHAR(100) NOT NULL,
+    "last_used_at" TIMESTAMPTZ(3),
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY ("id")
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "webauthn_credentials.credential_id_unique" ON "webauthn_credentials"("credential_id");
+
+-- CreateIndex
+CREATE INDEX "webauthn_credentials.identity_id_index" ON "webauthn_credentials"("identity_id");
+
+-- AddForeignKey
+ALTER TABLE "webauthn_credentials" ADD FOREIGN KEY ("identity_id") REFERENCES "identities"("id") ON DELETE CASCADE ON UPDATE CASCADE;
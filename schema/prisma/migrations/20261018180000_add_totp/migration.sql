@@ -0,0 +1,29 @@
+-- CreateTable
+CREATE TABLE "totp_secrets" (
+    "identity_id" UUID NOT NULL,
+    "secret" BYTEA NOT NULL,
+    "last_used_step" BIGINT,
+    "confirmed_at" TIMESTAMPTZ(3),
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY ("identity_id")
+);
+
+-- CreateTable
+CREATE TABLE "totp_recovery_codes" (
+    "id" UUID NOT NULL,
+    "identity_id" UUID NOT NULL,
+    "code_hash" VARCHAR(64) NOT NULL,
+    "created_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY ("id")
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "totp_recovery_codes.identity_id_code_hash_unique" ON "totp_recovery_codes"("identity_id", "code_hash");
+
+-- AddForeignKey
+ALTER TABLE "totp_secrets" ADD FOREIGN KEY ("identity_id") REFERENCES "identities"("id") ON DELETE CASCADE ON UPDATE CASCADE;
+
+-- AddForeignKey
+ALTER TABLE "totp_recovery_codes" ADD FOREIGN KEY ("identity_id") REFERENCES "identities"("id") ON DELETE CASCADE ON UPDATE CASCADE;
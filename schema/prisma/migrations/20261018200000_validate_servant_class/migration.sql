@@ -0,0 +1,26 @@
+-- CreateTable
+CREATE TABLE "quarantined_servants" (
+    "id" INTEGER NOT NULL,
+    "name" VARCHAR(100) NOT NULL,
+    "class_name" VARCHAR(64) NOT NULL,
+    "owner_id" UUID,
+    "reason" VARCHAR(64) NOT NULL,
+    "quarantined_at" TIMESTAMPTZ(3) NOT NULL DEFAULT CURRENT_TIMESTAMP,
+
+    PRIMARY KEY ("id")
+);
+
+-- NormalizeData
+UPDATE "servants" SET "class_name" = lower(btrim("class_name"));
+
+-- QuarantineData
+WITH "invalid" AS (
+    DELETE FROM "servants"
+    WHERE "class_name" NOT IN ('saber', 'archer', 'lancer', 'rider', 'caster', 'assassin', 'berserker', 'ruler', 'avenger', 'mooncancer', 'alterego', 'foreigner', 'pretender', 'shielder')
+    RETURNING "id", "name", "class_name", "owner_id"
+)
+INSERT INTO "quarantined_servants" ("id", "name", "class_name", "owner_id", "reason")
+    SELECT "id", "name", "class_name", "owner_id", 'invalid_class' FROM "invalid";
+
+-- AddCheckConstraint
+ALTER TABLE "servants" ADD CONSTRAINT "servants.class_name_check" CHECK ("class_name" IN ('saber', 'archer', 'lancer', 'rider', 'caster', 'assassin', 'berserker', 'ruler', 'avenger', 'mooncancer', 'alterego', 'foreigner', 'pretender', 'shielder'));
@@ -0,0 +1,29 @@
+-- BackfillData
+-- Servants created before ownership existed go to an admin chosen by the
+-- operator. With any such rows present, set the owner before migrating:
+--   ALTER DATABASE <database> SET actixexp.legacy_servant_owner = '<admin identity id>';
+-- The migration stops rather than guess when it is unset.
+DO $$
+DECLARE
+    ownerless BIGINT;
+    legacy_owner UUID;
+BEGIN
+    SELECT count(*) INTO ownerless FROM "servants" WHERE "owner_id" IS NULL;
+    IF ownerless = 0 THEN
+        RETURN;
+    END IF;
+
+    legacy_owner := NULLIF(current_setting('actixexp.legacy_servant_owner', true), '')::UUID;
+    IF legacy_owner IS NULL THEN
+        RAISE EXCEPTION '% servants have no owner', ownerless
+            USING HINT = 'Run ALTER DATABASE <database> SET actixexp.legacy_servant_owner = ''<admin identity id>'', reconnect, and migrate again.';
+    END IF;
+    IF NOT EXISTS (SELECT 1 FROM "identities" WHERE "id" = legacy_owner AND "role" = 'admin') THEN
+        RAISE EXCEPTION 'actixexp.legacy_servant_owner (%) is not an admin identity', legacy_owner;
+    END IF;
+
+    UPDATE "servants" SET "owner_id" = legacy_owner WHERE "owner_id" IS NULL;
+END $$;
+
+-- AlterTable
+ALTER TABLE "servants" ALTER COLUMN "owner_id" SET NOT NULL;
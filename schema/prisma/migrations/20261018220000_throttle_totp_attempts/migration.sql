@@ -0,0 +1,3 @@
+-- AlterTable
+ALTER TABLE "totp_secrets" ADD COLUMN "failed_attempts" INTEGER NOT NULL DEFAULT 0,
+ADD COLUMN "locked_until" TIMESTAMPTZ(3);
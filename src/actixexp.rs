@@ -1,61 +1,75 @@
 use actix_cors::Cors;
 use actix_web::http::header;
-use actix_session::{SessionMiddleware, storage::CookieSessionStore};
+use actix_session::SessionMiddleware;
+use actix_session::config::PersistentSession;
 use actix_web::{App, HttpServer};
-use actix_web::cookie::Key;
+use actix_web::cookie::{time, Key};
 use actix_web::middleware::Logger;
-use actix_web::web::{post, resource, scope, Data};
-use app::middlewares::LoginRequired;
+use actix_web::web::Data;
+use actixexp::app::db::session_store::{self, PostgresSessionStore};
+use std::time::Duration;
 use env_logger::Env;
 
-mod app;
-use self::app::config::ApplicationConfig;
-use self::app::context::Context;
-use self::app::config::AppArgs;
-use self::app::handlers::{self};
+use actixexp::app::config::ApplicationConfig;
+use actixexp::app::context::Context;
+use actixexp::app::config::AppArgs;
+use actixexp::app::handlers::{self};
+use actixexp::app::middlewares::{CSRF_HEADER, SessionKeyRotation};
 
 fn create_cors(config: &ApplicationConfig) -> Cors {
     Cors::default()
         .allowed_origin(&config.frontend.base_uri)
-        .allowed_methods(vec!["POST", "GET", "OPTIONS"])
+        .allowed_methods(vec!["POST", "GET", "PUT", "PATCH", "DELETE", "OPTIONS"])
         .allowed_headers(vec![header::CONTENT_TYPE])
+        .allowed_header(CSRF_HEADER)
         .supports_credentials()
 }
 
+fn create_session(config: &ApplicationConfig, store: PostgresSessionStore, key: Key) -> SessionMiddleware<PostgresSessionStore> {
+    let session = &config.session;
+    let lifecycle = PersistentSession::default()
+        .session_ttl(time::Duration::seconds(session.absolute_timeout_seconds));
+    SessionMiddleware::builder(store, key)
+        .cookie_name(session.cookie_name.clone())
+        .cookie_domain(session.cookie_domain.clone())
+        .cookie_path(session.cookie_path.clone())
+        .cookie_secure(session.cookie_secure)
+        .cookie_same_site(session.same_site())
+        .session_lifecycle(lifecycle)
+        .build()
+}
+
+const SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(600);
+
 #[actix_rt::main]
 async fn main() -> anyhow::Result<()> {
     let args = AppArgs::new();
     let config = args.load_config().await?;
     let context = Context::initialize(&config)?;
     let bind_address = config.server.bind_address();
-    let session_key = config.app.raw_session_key()?;
+    let session_keys = config.app.session_keys()?;
+    config.session.validate()?;
+    if let Some(token) = &config.token {
+        token.raw_signing_key()?;
+    }
 
     env_logger::Builder::from_env(Env::default().default_filter_or("info")).init();
 
+    actix_rt::spawn(session_store::sweep_expired_sessions(context.db.clone(), SESSION_SWEEP_INTERVAL));
+
     let server = HttpServer::new(move || {
-        let session = SessionMiddleware::new(CookieSessionStore::default(), Key::from(&session_key));
+        let session_store = PostgresSessionStore::new(context.db.clone());
+        let session = create_session(&config, session_store, session_keys[0].clone());
+        let key_rotation = SessionKeyRotation::new(session_keys.clone(), &config.session);
         let cors = create_cors(&config);
-        let login_required = LoginRequired::new();
 
         App::new()
             .wrap(Logger::new("%a %t \"%r\" %s %b \"%{Referer}i\" \"%{User-Agent}i\" %T"))
             .wrap(cors)
             .wrap(session)
+            .wrap(key_rotation)
             .app_data(Data::new(context.clone()))
-            .service(handlers::root::index)
-            .service(
-                scope("/auth")
-                    .configure(handlers::auth_service_config)
-            )
-            .service(
-                scope("/servants")
-                    .wrap(login_required)
-                    .configure(handlers::servant_service_config)
-            )
-            .service(
-                resource("/signout")
-                    .route(post().to(handlers::sessions::signout))
-            )
+            .configure(handlers::app_config)
     });
     server.bind(bind_address)?.run().await?;
 
@@ -1,6 +1,7 @@
 pub mod config;
 pub mod context;
 pub mod db;
+pub mod extractors;
 pub mod handlers;
 pub mod middlewares;
 pub mod models;
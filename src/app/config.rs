@@ -11,12 +11,18 @@ mod auth;
 mod database;
 mod frontend;
 mod server;
+mod session;
+mod token;
+mod webauthn;
 
 pub use self::app::AppConfig;
-pub use self::auth::AuthConfig;
+pub use self::auth::{AuthConfig, ProviderConfig, ProviderKind};
 pub use self::database::DatabaseConfig;
 pub use self::frontend::FrontendConfig;
 pub use self::server::ServerConfig;
+pub use self::session::{SameSitePolicy, SessionConfig};
+pub use self::token::TokenConfig;
+pub use self::webauthn::WebAuthnConfig;
 
 #[derive(Parser)]
 pub struct AppArgs {
@@ -25,6 +31,8 @@ pub struct AppArgs {
 }
 
 impl AppArgs {
+    // Reads the process arguments, which a `Default` impl should not do.
+    #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
         Self::parse()
     }
@@ -45,4 +53,9 @@ pub struct ApplicationConfig {
     pub auth: AuthConfig,
     pub database: DatabaseConfig,
     pub frontend: FrontendConfig,
+    #[serde(default)]
+    pub session: SessionConfig,
+    #[serde(default)]
+    pub webauthn: WebAuthnConfig,
+    pub token: Option<TokenConfig>,
 }
@@ -1,15 +1,32 @@
-use anyhow::{Context, Result};
+use actix_web::cookie::Key;
+use anyhow::{bail, Context, Result};
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use serde_derive::Deserialize;
 
+/// `session_keys` lists keys newest first: the first one seals new session
+/// cookies and the rest are only accepted, so a key can be rotated out
+/// without signing anyone out. A lone `session_key` is still accepted.
 #[derive(Clone, Debug, Deserialize)]
 pub struct AppConfig {
-    session_key: String,
+    session_key: Option<String>,
+    #[serde(default)]
+    session_keys: Vec<String>,
 }
 
 impl AppConfig {
-    pub fn raw_session_key(&self) -> Result<Vec<u8>> {
-        STANDARD.decode(&self.session_key)
-            .with_context(|| "Failed to parse session_key as Base64 string")
+    pub fn session_keys(&self) -> Result<Vec<Key>> {
+        let encoded = match (&self.session_key, self.session_keys.is_empty()) {
+            (_, false) => self.session_keys.clone(),
+            (Some(session_key), true) => vec![session_key.clone()],
+            (None, true) => bail!("Either session_keys or session_key must be set"),
+        };
+        encoded.iter()
+            .map(|key| {
+                let raw = STANDARD.decode(key)
+                    .with_context(|| "Failed to parse session key as Base64 string")?;
+                Key::try_from(raw.as_slice())
+                    .with_context(|| "Session key must be at least 64 bytes long")
+            })
+            .collect()
     }
 }
@@ -1,7 +1,79 @@
+use std::collections::HashMap;
+
 use serde_derive::Deserialize;
 
+/// Path segments under `/auth` that belong to fixed routes. A provider with
+/// one of these names would never be reached.
+const RESERVED_PROVIDER_NAMES: [&str; 5] = ["csrf", "session", "token", "totp", "webauthn"];
+
 #[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "HashMap<String, ProviderConfig>")]
 pub struct AuthConfig {
+    providers: HashMap<String, ProviderConfig>,
+}
+
+impl TryFrom<HashMap<String, ProviderConfig>> for AuthConfig {
+    type Error = String;
+
+    fn try_from(providers: HashMap<String, ProviderConfig>) -> Result<Self, Self::Error> {
+        if let Some(name) = providers.keys().find(|name| RESERVED_PROVIDER_NAMES.contains(&name.as_str())) {
+            return Err(format!("auth.{} is reserved and cannot name a provider", name))
+        }
+        Ok(Self { providers })
+    }
+}
+
+impl AuthConfig {
+    pub fn provider(&self, name: &str) -> Option<&ProviderConfig> {
+        self.providers.get(name)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Github,
+    Gitlab,
+    Oidc,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ProviderConfig {
+    pub kind: ProviderKind,
     pub client_id: String,
     pub client_secret: String,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub base_url: Option<String>,
+    pub issuer: Option<String>,
+    pub authorize_url: Option<String>,
+    pub token_url: Option<String>,
+    pub user_url: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(name: &str) -> Result<AuthConfig, toml::de::Error> {
+        toml::from_str(&format!(r#"
+            [{}]
+            kind = "github"
+            client_id = "client"
+            client_secret = "secret"
+        "#, name))
+    }
+
+    #[test]
+    fn accepts_ordinary_provider_names() {
+        assert!(parse("github").unwrap().provider("github").is_some());
+    }
+
+    #[test]
+    fn rejects_reserved_provider_names() {
+        for name in RESERVED_PROVIDER_NAMES {
+            let error = parse(name).err().unwrap();
+            assert!(error.to_string().contains("is reserved"), "{}", error);
+        }
+    }
 }
@@ -3,4 +3,6 @@ use serde_derive::Deserialize;
 #[derive(Clone, Debug, Deserialize)]
 pub struct FrontendConfig {
     pub base_uri: String,
+    #[serde(default)]
+    pub allowed_return_origins: Vec<String>,
 }
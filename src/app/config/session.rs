@@ -0,0 +1,86 @@
+use actix_web::cookie::SameSite;
+use anyhow::{bail, Result};
+use serde_derive::Deserialize;
+
+/// Session cookie policy and lifetimes. The absolute timeout caps a
+/// sign-in regardless of activity; the idle timeout ends it after a quiet
+/// period.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SessionConfig {
+    #[serde(default = "default_cookie_name")]
+    pub cookie_name: String,
+    pub cookie_domain: Option<String>,
+    #[serde(default = "default_cookie_path")]
+    pub cookie_path: String,
+    #[serde(default = "default_cookie_secure")]
+    pub cookie_secure: bool,
+    #[serde(default)]
+    pub cookie_same_site: SameSitePolicy,
+    #[serde(default = "default_absolute_timeout_seconds")]
+    pub absolute_timeout_seconds: i64,
+    #[serde(default = "default_idle_timeout_seconds")]
+    pub idle_timeout_seconds: i64,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSitePolicy {
+    Strict,
+    #[default]
+    Lax,
+    None,
+}
+
+impl SessionConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.absolute_timeout_seconds <= 0 || self.idle_timeout_seconds <= 0 {
+            bail!("Session timeouts must be positive")
+        }
+        if self.cookie_same_site == SameSitePolicy::None && !self.cookie_secure {
+            bail!("Session cookie_same_site = \"none\" requires cookie_secure")
+        }
+        Ok(())
+    }
+
+    pub fn same_site(&self) -> SameSite {
+        match self.cookie_same_site {
+            SameSitePolicy::Strict => SameSite::Strict,
+            SameSitePolicy::Lax => SameSite::Lax,
+            SameSitePolicy::None => SameSite::None,
+        }
+    }
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            cookie_name: default_cookie_name(),
+            cookie_domain: None,
+            cookie_path: default_cookie_path(),
+            cookie_secure: default_cookie_secure(),
+            cookie_same_site: SameSitePolicy::default(),
+            absolute_timeout_seconds: default_absolute_timeout_seconds(),
+            idle_timeout_seconds: default_idle_timeout_seconds(),
+        }
+    }
+}
+
+fn default_cookie_name() -> String {
+    "id".to_owned()
+}
+
+fn default_cookie_path() -> String {
+    "/".to_owned()
+}
+
+fn default_cookie_secure() -> bool {
+    true
+}
+
+fn default_absolute_timeout_seconds() -> i64 {
+    86400
+}
+
+fn default_idle_timeout_seconds() -> i64 {
+    1800
+}
@@ -0,0 +1,65 @@
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use serde_derive::Deserialize;
+
+/// HS256 keys shorter than the hash output weaken the signature.
+const MIN_SIGNING_KEY_LENGTH: usize = 32;
+
+/// Settings for issuing access/refresh token pairs to clients that cannot
+/// keep a session cookie. Token mode is off unless this section is present.
+#[derive(Clone, Debug, Deserialize)]
+pub struct TokenConfig {
+    signing_key: String,
+    #[serde(default = "default_access_ttl_seconds")]
+    pub access_ttl_seconds: i64,
+    #[serde(default = "default_refresh_ttl_days")]
+    pub refresh_ttl_days: i64,
+}
+
+impl TokenConfig {
+    pub fn raw_signing_key(&self) -> Result<Vec<u8>> {
+        let key = STANDARD.decode(&self.signing_key)
+            .with_context(|| "Failed to parse token signing_key as Base64 string")?;
+        if key.len() < MIN_SIGNING_KEY_LENGTH {
+            bail!("Token signing_key must be at least {} bytes", MIN_SIGNING_KEY_LENGTH)
+        }
+        Ok(key)
+    }
+}
+
+fn default_access_ttl_seconds() -> i64 {
+    900
+}
+
+fn default_refresh_ttl_days() -> i64 {
+    30
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(signing_key: &[u8]) -> TokenConfig {
+        TokenConfig {
+            signing_key: STANDARD.encode(signing_key),
+            access_ttl_seconds: default_access_ttl_seconds(),
+            refresh_ttl_days: default_refresh_ttl_days(),
+        }
+    }
+
+    #[test]
+    fn accepts_keys_of_at_least_32_bytes() {
+        assert_eq!(config(&[7; 32]).raw_signing_key().unwrap(), vec![7; 32]);
+        assert_eq!(config(&[7; 64]).raw_signing_key().unwrap().len(), 64);
+    }
+
+    #[test]
+    fn rejects_short_or_malformed_keys() {
+        assert!(config(&[7; 31]).raw_signing_key().is_err());
+        assert!(config(b"").raw_signing_key().is_err());
+
+        let mut malformed = config(&[7; 32]);
+        malformed.signing_key = "not base64!".to_owned();
+        assert!(malformed.raw_signing_key().is_err());
+    }
+}
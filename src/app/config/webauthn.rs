@@ -0,0 +1,33 @@
+use reqwest::Url;
+use serde_derive::Deserialize;
+
+use super::FrontendConfig;
+
+/// Relying party settings for passkeys. Every field falls back to one
+/// derived from the frontend base URI, so the section is optional.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct WebAuthnConfig {
+    pub rp_id: Option<String>,
+    pub rp_name: Option<String>,
+    #[serde(default)]
+    pub origins: Vec<String>,
+}
+
+impl WebAuthnConfig {
+    pub fn rp_id(&self, frontend: &FrontendConfig) -> String {
+        self.rp_id.clone()
+            .or_else(|| Url::parse(&frontend.base_uri).ok()?.host_str().map(str::to_owned))
+            .unwrap_or_else(|| "localhost".to_owned())
+    }
+
+    pub fn rp_name(&self) -> String {
+        self.rp_name.clone().unwrap_or_else(|| "Actixexp".to_owned())
+    }
+
+    pub fn origins(&self, frontend: &FrontendConfig) -> Vec<String> {
+        match self.origins.is_empty() {
+            true => vec![frontend.base_uri.trim_end_matches('/').to_owned()],
+            false => self.origins.clone(),
+        }
+    }
+}
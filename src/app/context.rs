@@ -1,11 +1,12 @@
 use anyhow::Result;
 
-use super::{config::ApplicationConfig, db::connection::RepositoryAccess};
+use super::{config::ApplicationConfig, db::connection::RepositoryAccess, models::auth::DiscoveryCache};
 
 #[derive(Clone)]
 pub struct Context {
     pub config: ApplicationConfig,
     pub db: RepositoryAccess,
+    pub discovery: DiscoveryCache,
 }
 
 impl Context {
@@ -13,7 +14,8 @@ impl Context {
         let db = RepositoryAccess::initialize(&config.database)?;
         let context = Self {
             config: config.clone(),
-            db: db,
+            db,
+            discovery: DiscoveryCache::new(),
         };
         Ok(context)
     }
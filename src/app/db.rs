@@ -1,8 +1,15 @@
 use thiserror::Error;
 
+pub mod access_token_repository;
 pub mod connection;
+pub mod credential_repository;
 pub mod identity_repository;
+pub mod refresh_token_repository;
 pub mod servant_repository;
+pub mod session_repository;
+pub mod session_store;
+pub mod totp_repository;
+pub mod webauthn_credential_repository;
 
 #[derive(Error, Debug)]
 pub enum DatabaseError {
@@ -0,0 +1,111 @@
+use deadpool_postgres::Client;
+use serde_derive::{Deserialize, Serialize};
+use tokio_pg_mapper::FromTokioPostgresRow;
+use tokio_pg_mapper_derive::PostgresMapper;
+use tokio_postgres::Row;
+
+use super::DatabaseError;
+use super::connection::DatabaseConnection;
+
+type Result<T, E = DatabaseError> = std::result::Result<T, E>;
+
+#[derive(Deserialize, PostgresMapper, Serialize)]
+#[pg_mapper(table = "access_tokens")]
+pub struct AccessToken {
+    id: String,
+    name: String,
+    scopes: Vec<String>,
+    last_used_at: Option<String>,
+    expires_at: Option<String>,
+    created_at: String,
+}
+
+pub struct AccessTokenDataset {
+    pub name: String,
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    pub ttl_days: Option<i64>,
+}
+
+/// Identity and granted scopes behind a presented access token.
+pub struct TokenGrant {
+    pub identity_id: String,
+    pub scopes: Vec<String>,
+}
+
+const ACCESS_TOKEN_COLUMNS: &str =
+    "cast(id as varchar) as id, name, coalesce(scopes, '{}') as scopes,
+        to_char(last_used_at at time zone 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"') as last_used_at,
+        to_char(expires_at at time zone 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"') as expires_at,
+        to_char(created_at at time zone 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"') as created_at";
+
+pub struct AccessTokenRepository<'a> {
+    client: &'a Client,
+}
+
+impl<'a> AccessTokenRepository<'a> {
+    pub fn new(connection: &'a DatabaseConnection) -> Self {
+        Self {
+            client: connection,
+        }
+    }
+
+    pub async fn create(&self, identity_id: &str, dataset: &AccessTokenDataset) -> Result<AccessToken> {
+        let statement = format!(
+            "insert into access_tokens (id, identity_id, name, token_hash, scopes, expires_at)
+                values (gen_random_uuid(), $1::varchar::uuid, $2, $3, $4, now() + $5::int8 * interval '1 day')
+                returning {}",
+            ACCESS_TOKEN_COLUMNS);
+        let row = self.client.query_one(statement.as_str(), &[&identity_id, &dataset.name, &dataset.token_hash, &dataset.scopes, &dataset.ttl_days]).await?;
+        row.try_into()
+    }
+
+    pub async fn list(&self, identity_id: &str) -> Result<Vec<AccessToken>> {
+        let statement = format!(
+            "select {} from access_tokens
+                where identity_id = $1::varchar::uuid
+                order by created_at desc",
+            ACCESS_TOKEN_COLUMNS);
+        let rows = self.client.query(statement.as_str(), &[&identity_id]).await?;
+
+        let tokens = rows.iter()
+            .map(AccessToken::from_row_ref)
+            .collect::<Result<Vec<AccessToken>, _>>()?;
+        Ok(tokens)
+    }
+
+    pub async fn delete(&self, identity_id: &str, id: &str) -> Result<AccessToken> {
+        let statement = format!(
+            "delete from access_tokens
+                where identity_id = $1::varchar::uuid and cast(id as varchar) = $2
+                returning {}",
+            ACCESS_TOKEN_COLUMNS);
+        let row = self.client.query_opt(statement.as_str(), &[&identity_id, &id]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        row.try_into()
+    }
+
+    /// Looks up an unexpired token by its hash and records its use.
+    pub async fn authenticate(&self, token_hash: &str) -> Result<TokenGrant> {
+        let statement =
+            "update access_tokens
+                set last_used_at = now()
+                where token_hash = $1 and (expires_at is null or expires_at > now())
+                returning cast(identity_id as varchar) as identity_id, coalesce(scopes, '{}') as scopes";
+        let row = self.client.query_opt(statement, &[&token_hash]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        let grant = TokenGrant {
+            identity_id: row.try_get("identity_id")?,
+            scopes: row.try_get("scopes")?,
+        };
+        Ok(grant)
+    }
+}
+
+impl TryFrom<Row> for AccessToken {
+    type Error = DatabaseError;
+
+    fn try_from(value: Row) -> Result<Self, Self::Error> {
+        Ok(Self::from_row(value)?)
+    }
+}
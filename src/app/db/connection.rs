@@ -18,12 +18,12 @@ pub struct RepositoryAccess {
 impl RepositoryAccess {
     fn new(pool: Pool) -> Self {
         Self {
-            pool: pool,
+            pool,
         }
     }
 
     pub fn initialize(config: &DatabaseConfig) -> Result<Self> {
-        let pool = Self::create_pool(&config)?;
+        let pool = Self::create_pool(config)?;
         Ok(Self::new(pool))
     }
 
@@ -57,7 +57,7 @@ pub struct DatabaseConnection {
 impl DatabaseConnection {
     fn new(client: Client) -> Self {
         Self {
-            client: client,
+            client,
         }
     }
 }
@@ -0,0 +1,98 @@
+use deadpool_postgres::Client;
+use serde_derive::{Deserialize, Serialize};
+use tokio_pg_mapper::FromTokioPostgresRow;
+use tokio_pg_mapper_derive::PostgresMapper;
+use tokio_postgres::Row;
+
+use super::DatabaseError;
+use super::connection::DatabaseConnection;
+
+type Result<T, E = DatabaseError> = std::result::Result<T, E>;
+
+#[derive(Deserialize, PostgresMapper, Serialize)]
+#[pg_mapper(table = "credentials")]
+pub struct LinkedCredential {
+    id: String,
+    provider_identifier: String,
+    login: Option<String>,
+    linked_at: String,
+}
+
+const LINKED_CREDENTIAL_COLUMNS: &str =
+    "cast(id as varchar) as id, provider_identifier, login,
+        to_char(linked_at at time zone 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"') as linked_at";
+
+pub struct CredentialRepository<'a> {
+    client: &'a Client,
+}
+
+impl<'a> CredentialRepository<'a> {
+    pub fn new(connection: &'a DatabaseConnection) -> Self {
+        Self {
+            client: connection,
+        }
+    }
+
+    /// Links a provider credential to `identity_id`. Returns `None` when the
+    /// credential already belongs to another identity.
+    pub async fn link(&self, identity_id: &str, provider_identifier: &str, login: &str) -> Result<Option<LinkedCredential>> {
+        let statement = format!(
+            "insert into credentials (id, identity_id, provider_identifier, login)
+                values (gen_random_uuid(), $1::varchar::uuid, $2, $3)
+                on conflict (provider_identifier) do update
+                  set login = excluded.login
+                  where credentials.identity_id = excluded.identity_id
+                returning {}",
+            LINKED_CREDENTIAL_COLUMNS);
+        let row = self.client.query_opt(statement.as_str(), &[&identity_id, &provider_identifier, &login]).await?;
+        row.map(LinkedCredential::try_from).transpose()
+    }
+
+    pub async fn list(&self, identity_id: &str) -> Result<Vec<LinkedCredential>> {
+        let statement = format!(
+            "select {} from credentials
+                where identity_id = $1::varchar::uuid
+                order by linked_at",
+            LINKED_CREDENTIAL_COLUMNS);
+        let rows = self.client.query(statement.as_str(), &[&identity_id]).await?;
+
+        let credentials = rows.iter()
+            .map(LinkedCredential::from_row_ref)
+            .collect::<Result<Vec<LinkedCredential>, _>>()?;
+        Ok(credentials)
+    }
+
+    pub async fn show(&self, identity_id: &str, id: &str) -> Result<LinkedCredential> {
+        let statement = format!(
+            "select {} from credentials
+                where identity_id = $1::varchar::uuid and cast(id as varchar) = $2",
+            LINKED_CREDENTIAL_COLUMNS);
+        let row = self.client.query_opt(statement.as_str(), &[&identity_id, &id]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        row.try_into()
+    }
+
+    /// Unlinks a credential unless it is the last one of the identity.
+    /// Locking the identity's credentials first keeps two concurrent
+    /// unlinks from removing both of the last two.
+    pub async fn delete_unless_last(&self, identity_id: &str, id: &str) -> Result<Option<LinkedCredential>> {
+        let statement = format!(
+            "delete from credentials
+                where identity_id = $1::varchar::uuid and cast(id as varchar) = $2
+                  and (select count(*) from (
+                    select 1 from credentials where identity_id = $1::varchar::uuid for update
+                  ) as remaining) > 1
+                returning {}",
+            LINKED_CREDENTIAL_COLUMNS);
+        let row = self.client.query_opt(statement.as_str(), &[&identity_id, &id]).await?;
+        row.map(LinkedCredential::try_from).transpose()
+    }
+}
+
+impl TryFrom<Row> for LinkedCredential {
+    type Error = DatabaseError;
+
+    fn try_from(value: Row) -> Result<Self, Self::Error> {
+        Ok(Self::from_row(value)?)
+    }
+}
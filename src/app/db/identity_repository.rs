@@ -1,13 +1,24 @@
 use deadpool_postgres::Client;
 use tokio_pg_mapper::FromTokioPostgresRow;
 use tokio_postgres::Row;
+use tokio_postgres::types::{FromSql, Type};
 
 use crate::app::models::Identity;
+use crate::app::models::identity::Role;
 
 use super::{DatabaseError, connection::DatabaseConnection};
 
 type Result<T, E = DatabaseError> = std::result::Result<T, E>;
 
+pub struct ProfileDataset {
+    pub login: String,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+const IDENTITY_COLUMNS: &str =
+    "cast(id as varchar) as id, alive, role, login, name, avatar_url";
+
 pub struct IdentityRepository<'a> {
     client: &'a Client,
 }
@@ -19,48 +30,69 @@ impl<'a> IdentityRepository<'a> {
         }
     }
 
-    pub async fn find_by_provider_identifier(&self, identifier: &str) -> Result<Option<Identity>> {
-        let statement =
-            "select cast(id as varchar) as id, provider_identifier, alive
-                from identities where provider_identifier = $1
-                limit 1";
-        let result = self.client.query_opt(statement, &[&identifier]).await?;
-        match result {
-            Some(row) => {
-                let identity = row.try_into()?;
-                Ok(Some(identity))
-            },
-            None => {
-                Ok(None)
-            }
-        }
-    }
-
-    #[allow(dead_code)]
     pub async fn find_by_id(&self, id: &str) -> Result<Identity> {
-        let statement =
-            "select cast(id as varchar) as id, provider_identifier, alive
-                from identities where id = $1
-                limit 1";
-        let row = self.client.query_one(statement, &[&id]).await?;
+        let statement = format!(
+            "select {} from identities where id = $1::varchar::uuid
+                limit 1",
+            IDENTITY_COLUMNS);
+        let row = self.client.query_opt(statement.as_str(), &[&id]).await?
+            .ok_or(DatabaseError::NotFound)?;
         row.try_into()
     }
 
-    pub async fn create(&self, identifier: &str) -> Result<Identity> {
-        let statement =
-            "insert into identities (id, provider_identifier)
-               values (gen_random_uuid(), $1)
-               returning id, provider_identifier, alive, registered_at";
-        let row = self.client.query_one(statement, &[&identifier]).await?;
+    pub async fn list(&self) -> Result<Vec<Identity>> {
+        let statement = format!(
+            "select {} from identities order by registered_at desc",
+            IDENTITY_COLUMNS);
+        let rows = self.client.query(statement.as_str(), &[]).await?;
+
+        let identities = rows.iter()
+            .map(Identity::from_row_ref)
+            .collect::<Result<Vec<Identity>, _>>()?;
+        Ok(identities)
+    }
+
+    /// Signs in through a provider credential: refreshes the profile of the
+    /// identity it is linked to, or registers a new identity with it.
+    pub async fn register(&self, provider_identifier: &str, profile: &ProfileDataset) -> Result<Identity> {
+        let statement = format!(
+            "with credential as (
+                  update credentials set login = $2
+                    where provider_identifier = $1
+                    returning identity_id
+                ),
+                updated as (
+                  update identities set login = $2, name = $3, avatar_url = $4
+                    where id = (select identity_id from credential)
+                    returning {columns}
+                ),
+                inserted as (
+                  insert into identities (id, login, name, avatar_url)
+                    select gen_random_uuid(), $2, $3, $4
+                    where not exists (select 1 from credential)
+                    returning {columns}
+                ),
+                linked as (
+                  insert into credentials (id, identity_id, provider_identifier, login)
+                    select gen_random_uuid(), cast(id as uuid), $1, $2 from inserted
+                )
+                select * from updated
+                union all
+                select * from inserted",
+            columns = IDENTITY_COLUMNS);
+        let row = self.client.query_one(statement.as_str(), &[&provider_identifier, &profile.login, &profile.name, &profile.avatar_url]).await?;
         row.try_into()
     }
 
-    pub async fn find_or_create(&self, provider_identifier: &str) -> Result<Identity> {
-        let existing_identity = self.find_by_provider_identifier(provider_identifier).await?;
-        match existing_identity {
-            Some(identity) => Ok(identity),
-            None => self.create(provider_identifier).await,
-        }
+    pub async fn set_alive(&self, id: &str, alive: bool) -> Result<Identity> {
+        let statement = format!(
+            "update identities set alive = $2
+                where id = $1::varchar::uuid
+                returning {}",
+            IDENTITY_COLUMNS);
+        let row = self.client.query_opt(statement.as_str(), &[&id, &alive]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        row.try_into()
     }
 }
 
@@ -71,3 +103,14 @@ impl TryFrom<Row> for Identity {
         Ok(Self::from_row(value)?)
     }
 }
+
+impl<'a> FromSql<'a> for Role {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> std::result::Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        let value = <&str as FromSql>::from_sql(ty, raw)?;
+        Ok(value.parse()?)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <&str as FromSql>::accepts(ty)
+    }
+}
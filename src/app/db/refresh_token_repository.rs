@@ -0,0 +1,78 @@
+use deadpool_postgres::Client;
+
+use super::DatabaseError;
+use super::connection::DatabaseConnection;
+
+type Result<T, E = DatabaseError> = std::result::Result<T, E>;
+
+/// The token family and identity a consumed refresh token belonged to.
+pub struct RefreshGrant {
+    pub family_id: String,
+    pub identity_id: String,
+}
+
+pub struct RefreshTokenRepository<'a> {
+    client: &'a Client,
+}
+
+impl<'a> RefreshTokenRepository<'a> {
+    pub fn new(connection: &'a DatabaseConnection) -> Self {
+        Self {
+            client: connection,
+        }
+    }
+
+    /// Stores a refresh token, starting a new family unless `family_id` is
+    /// given. Returns the family ID.
+    pub async fn create(&self, identity_id: &str, family_id: Option<&str>, token_hash: &str, ttl_days: i64) -> Result<String> {
+        let statement =
+            "insert into refresh_tokens (id, family_id, identity_id, token_hash, expires_at)
+                values (gen_random_uuid(), coalesce($2::varchar::uuid, gen_random_uuid()), $1::varchar::uuid, $3, now() + $4::int8 * interval '1 day')
+                returning cast(family_id as varchar) as family_id";
+        let row = self.client.query_one(statement, &[&identity_id, &family_id, &token_hash, &ttl_days]).await?;
+        Ok(row.try_get("family_id")?)
+    }
+
+    /// Marks a live refresh token as used. Each token can be consumed once;
+    /// `NotFound` covers unknown, expired, revoked and already used tokens.
+    pub async fn consume(&self, token_hash: &str) -> Result<RefreshGrant> {
+        let statement =
+            "update refresh_tokens set used_at = now()
+                where token_hash = $1 and used_at is null and revoked_at is null and expires_at > now()
+                returning cast(family_id as varchar) as family_id, cast(identity_id as varchar) as identity_id";
+        let row = self.client.query_opt(statement, &[&token_hash]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        let grant = RefreshGrant {
+            family_id: row.try_get("family_id")?,
+            identity_id: row.try_get("identity_id")?,
+        };
+        Ok(grant)
+    }
+
+    /// Returns the family of a token that was already rotated, which means
+    /// it is being presented a second time.
+    pub async fn find_reused_family(&self, token_hash: &str) -> Result<Option<String>> {
+        let statement =
+            "select cast(family_id as varchar) as family_id from refresh_tokens
+                where token_hash = $1 and used_at is not null";
+        let row = self.client.query_opt(statement, &[&token_hash]).await?;
+        row.map(|row| row.try_get("family_id")).transpose().map_err(DatabaseError::from)
+    }
+
+    pub async fn find_family(&self, token_hash: &str) -> Result<String> {
+        let statement =
+            "select cast(family_id as varchar) as family_id from refresh_tokens
+                where token_hash = $1";
+        let row = self.client.query_opt(statement, &[&token_hash]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        Ok(row.try_get("family_id")?)
+    }
+
+    pub async fn revoke_family(&self, family_id: &str) -> Result<()> {
+        let statement =
+            "update refresh_tokens set revoked_at = now()
+                where family_id = $1::varchar::uuid and revoked_at is null";
+        self.client.execute(statement, &[&family_id]).await?;
+        Ok(())
+    }
+}
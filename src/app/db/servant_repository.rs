@@ -3,6 +3,10 @@ use serde_derive::{Deserialize, Serialize};
 use tokio_pg_mapper::FromTokioPostgresRow;
 use tokio_pg_mapper_derive::PostgresMapper;
 use tokio_postgres::Row;
+use tokio_postgres::types::{FromSql, ToSql, Type};
+
+use crate::app::models::servant::ServantClass;
+use crate::app::models::servant::listing::{SortColumn, SortOrder};
 
 use super::DatabaseError;
 use super::connection::DatabaseConnection;
@@ -14,12 +18,47 @@ type Result<T, E = DatabaseError> = std::result::Result<T, E>;
 pub struct Servant {
     id: i32,
     name: String,
-    class_name: String,
+    class_name: ServantClass,
+}
+
+impl Servant {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn class_name(&self) -> ServantClass {
+        self.class_name
+    }
 }
 
 pub struct RegistrationDataset {
+    pub owner_id: String,
     pub name: String,
-    pub class_name: String,
+    pub class_name: ServantClass,
+}
+
+pub struct UpdateDataset {
+    pub name: Option<String>,
+    pub class_name: Option<ServantClass>,
+}
+
+pub struct ListingDataset {
+    pub owner_id: String,
+    pub limit: i64,
+    pub classes: Option<Vec<&'static str>>,
+    pub name_pattern: Option<String>,
+    pub sort_column: SortColumn,
+    pub sort_order: SortOrder,
+    pub after: Option<ListingPosition>,
+}
+
+pub struct ListingPosition {
+    pub id: i32,
+    pub key: String,
 }
 
 pub struct ServantRepository<'a> {
@@ -34,31 +73,95 @@ impl<'a> ServantRepository<'a> {
     }
 
     pub async fn create(&self, dataset: RegistrationDataset) -> Result<Servant> {
-        let statement = "insert into servants (name, class_name) values ($1, $2) returning id, name, class_name";
-        let row = self.client.query_one(statement, &[&dataset.name, &dataset.class_name]).await?;
+        let statement =
+            "insert into servants (owner_id, name, class_name)
+                values ($1::varchar::uuid, $2, $3)
+                returning id, name, class_name";
+        let row = self.client.query_one(statement, &[&dataset.owner_id, &dataset.name, &dataset.class_name.as_str()]).await?;
         row.try_into()
     }
 
-    pub async fn list(&self) -> Result<Vec<Servant>> {
-        let statement = "select id, name, class_name from servants";
-        let rows = self.client.query(statement, &[]).await?;
+    pub async fn list(&self, dataset: ListingDataset) -> Result<Vec<Servant>> {
+        let mut conditions: Vec<String> = vec!["owner_id = $1::varchar::uuid".to_owned()];
+        let mut params: Vec<&(dyn ToSql + Sync)> = vec![&dataset.owner_id];
+
+        if let Some(classes) = &dataset.classes {
+            params.push(classes);
+            conditions.push(format!("class_name = any(${})", params.len()));
+        }
+        if let Some(name_pattern) = &dataset.name_pattern {
+            params.push(name_pattern);
+            conditions.push(format!("name ilike ${} escape '\\'", params.len()));
+        }
+
+        let column = dataset.sort_column.column_name();
+        let (comparator, direction) = match dataset.sort_order {
+            SortOrder::Asc => (">", "asc"),
+            SortOrder::Desc => ("<", "desc"),
+        };
+        if let Some(position) = &dataset.after {
+            match dataset.sort_column {
+                SortColumn::Id => {
+                    params.push(&position.id);
+                    conditions.push(format!("id {} ${}", comparator, params.len()));
+                },
+                _ => {
+                    params.push(&position.key);
+                    params.push(&position.id);
+                    conditions.push(format!("({}, id) {} (${}::varchar, ${}::int4)", column, comparator, params.len() - 1, params.len()));
+                }
+            }
+        }
+
+        let where_clause = conditions.join(" and ");
+        let order_clause = match dataset.sort_column {
+            SortColumn::Id => format!("id {}", direction),
+            _ => format!("{} {}, id {}", column, direction, direction),
+        };
+        params.push(&dataset.limit);
+        let statement = format!(
+            "select id, name, class_name from servants
+                where {}
+                order by {}
+                limit ${}",
+            where_clause, order_clause, params.len());
+        let rows = self.client.query(statement.as_str(), &params).await?;
 
         let servants = rows.iter()
-            .map(|row| Servant::from_row_ref(row).unwrap())
-            .collect::<Vec<Servant>>();
+            .map(Servant::from_row_ref)
+            .collect::<Result<Vec<Servant>, _>>()?;
         Ok(servants)
     }
 
-    pub async fn show(&self, id: i32) -> Result<Servant> {
-        let statement = "select id, name, class_name from servants where id = $1";
-        let row = self.client.query_opt(statement, &[&id]).await?
+    /// Finds a servant by id, limited to `owner_id` unless it is `None`.
+    pub async fn show(&self, owner_id: Option<&str>, id: i32) -> Result<Servant> {
+        let statement =
+            "select id, name, class_name from servants
+                where id = $1 and ($2::varchar is null or owner_id = $2::varchar::uuid)";
+        let row = self.client.query_opt(statement, &[&id, &owner_id]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        row.try_into()
+    }
+
+    pub async fn update(&self, owner_id: &str, id: i32, dataset: UpdateDataset) -> Result<Servant> {
+        let statement =
+            "update servants
+                set name = coalesce($3, name), class_name = coalesce($4, class_name)
+                where id = $1 and owner_id = $2::varchar::uuid
+                returning id, name, class_name";
+        let class_name = dataset.class_name.map(|class| class.as_str());
+        let row = self.client.query_opt(statement, &[&id, &owner_id, &dataset.name, &class_name]).await?
             .ok_or(DatabaseError::NotFound)?;
         row.try_into()
     }
 
-    pub async fn delete(&self, id: i32) -> Result<Servant> {
-        let statement = "delete from servants where id = $1 returning id, name, class_name";
-        let row = self.client.query_opt(statement, &[&id]).await?
+    /// Deletes a servant by id, limited to `owner_id` unless it is `None`.
+    pub async fn delete(&self, owner_id: Option<&str>, id: i32) -> Result<Servant> {
+        let statement =
+            "delete from servants
+                where id = $1 and ($2::varchar is null or owner_id = $2::varchar::uuid)
+                returning id, name, class_name";
+        let row = self.client.query_opt(statement, &[&id, &owner_id]).await?
             .ok_or(DatabaseError::NotFound)?;
         row.try_into()
     }
@@ -71,3 +174,14 @@ impl TryFrom<Row> for Servant {
         Ok(Self::from_row(value)?)
     }
 }
+
+impl<'a> FromSql<'a> for ServantClass {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> std::result::Result<Self, Box<dyn std::error::Error + Sync + Send>> {
+        let value = <&str as FromSql>::from_sql(ty, raw)?;
+        Ok(value.parse()?)
+    }
+
+    fn accepts(ty: &Type) -> bool {
+        <&str as FromSql>::accepts(ty)
+    }
+}
@@ -0,0 +1,129 @@
+use deadpool_postgres::Client;
+use serde_derive::{Deserialize, Serialize};
+use tokio_pg_mapper::FromTokioPostgresRow;
+use tokio_pg_mapper_derive::PostgresMapper;
+use tokio_postgres::Row;
+
+use super::DatabaseError;
+use super::connection::DatabaseConnection;
+
+type Result<T, E = DatabaseError> = std::result::Result<T, E>;
+
+#[derive(Deserialize, PostgresMapper, Serialize)]
+#[pg_mapper(table = "sessions")]
+pub struct ActiveSession {
+    id: String,
+    created_at: String,
+    updated_at: String,
+    expires_at: String,
+}
+
+pub struct SessionDataset {
+    pub identity_id: Option<String>,
+    pub state: String,
+    pub ttl_seconds: i64,
+}
+
+const ACTIVE_SESSION_COLUMNS: &str =
+    "cast(id as varchar) as id,
+        to_char(created_at at time zone 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"') as created_at,
+        to_char(updated_at at time zone 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"') as updated_at,
+        to_char(expires_at at time zone 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"') as expires_at";
+
+pub struct SessionRepository<'a> {
+    client: &'a Client,
+}
+
+impl<'a> SessionRepository<'a> {
+    pub fn new(connection: &'a DatabaseConnection) -> Self {
+        Self {
+            client: connection,
+        }
+    }
+
+    pub async fn find_state(&self, session_key: &str) -> Result<Option<String>> {
+        let statement =
+            "select state from sessions
+                where session_key = $1 and expires_at > now()";
+        let row = self.client.query_opt(statement, &[&session_key]).await?;
+        Ok(row.map(|row| row.get("state")))
+    }
+
+    pub async fn create(&self, session_key: &str, dataset: &SessionDataset) -> Result<()> {
+        let statement =
+            "insert into sessions (id, session_key, identity_id, state, expires_at)
+                values (gen_random_uuid(), $1, $2::varchar::uuid, $3, now() + $4::int8 * interval '1 second')";
+        self.client.execute(statement, &[&session_key, &dataset.identity_id, &dataset.state, &dataset.ttl_seconds]).await?;
+        Ok(())
+    }
+
+    pub async fn update(&self, session_key: &str, dataset: &SessionDataset) -> Result<bool> {
+        let statement =
+            "update sessions
+                set identity_id = $2::varchar::uuid, state = $3,
+                  updated_at = now(), expires_at = now() + $4::int8 * interval '1 second'
+                where session_key = $1 and expires_at > now()";
+        let count = self.client.execute(statement, &[&session_key, &dataset.identity_id, &dataset.state, &dataset.ttl_seconds]).await?;
+        Ok(count > 0)
+    }
+
+    pub async fn touch(&self, session_key: &str, ttl_seconds: i64) -> Result<()> {
+        let statement =
+            "update sessions
+                set updated_at = now(), expires_at = now() + $2::int8 * interval '1 second'
+                where session_key = $1";
+        self.client.execute(statement, &[&session_key, &ttl_seconds]).await?;
+        Ok(())
+    }
+
+    pub async fn delete_by_key(&self, session_key: &str) -> Result<()> {
+        let statement = "delete from sessions where session_key = $1";
+        self.client.execute(statement, &[&session_key]).await?;
+        Ok(())
+    }
+
+    pub async fn delete_expired(&self) -> Result<u64> {
+        let statement = "delete from sessions where expires_at <= now()";
+        let count = self.client.execute(statement, &[]).await?;
+        Ok(count)
+    }
+
+    pub async fn list(&self, identity_id: &str) -> Result<Vec<ActiveSession>> {
+        let statement = format!(
+            "select {} from sessions
+                where identity_id = $1::varchar::uuid and expires_at > now()
+                order by updated_at desc",
+            ACTIVE_SESSION_COLUMNS);
+        let rows = self.client.query(statement.as_str(), &[&identity_id]).await?;
+
+        let sessions = rows.iter()
+            .map(ActiveSession::from_row_ref)
+            .collect::<Result<Vec<ActiveSession>, _>>()?;
+        Ok(sessions)
+    }
+
+    pub async fn delete(&self, identity_id: &str, id: &str) -> Result<ActiveSession> {
+        let statement = format!(
+            "delete from sessions
+                where identity_id = $1::varchar::uuid and cast(id as varchar) = $2
+                returning {}",
+            ACTIVE_SESSION_COLUMNS);
+        let row = self.client.query_opt(statement.as_str(), &[&identity_id, &id]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        row.try_into()
+    }
+
+    pub async fn delete_all(&self, identity_id: &str) -> Result<u64> {
+        let statement = "delete from sessions where identity_id = $1::varchar::uuid";
+        let count = self.client.execute(statement, &[&identity_id]).await?;
+        Ok(count)
+    }
+}
+
+impl TryFrom<Row> for ActiveSession {
+    type Error = DatabaseError;
+
+    fn try_from(value: Row) -> Result<Self, Self::Error> {
+        Ok(Self::from_row(value)?)
+    }
+}
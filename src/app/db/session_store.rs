@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::cookie::time::Duration;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::{RngExt, rngs::StdRng};
+
+use super::DatabaseError;
+use super::connection::RepositoryAccess;
+use super::session_repository::{SessionDataset, SessionRepository};
+
+type SessionState = HashMap<String, String>;
+
+#[derive(Clone)]
+pub struct PostgresSessionStore {
+    db: RepositoryAccess,
+}
+
+impl PostgresSessionStore {
+    pub fn new(db: RepositoryAccess) -> Self {
+        Self {
+            db,
+        }
+    }
+
+    fn generate_session_key() -> SessionKey {
+        let mut rng: StdRng = rand::make_rng();
+        let mut rs: [u8; 32] = [0; 32];
+        rng.fill(&mut rs);
+        URL_SAFE_NO_PAD.encode(rs)
+            .try_into()
+            .expect("generated session key should fit in a cookie")
+    }
+
+    fn create_dataset(session_state: &SessionState, ttl: &Duration) -> Result<SessionDataset, serde_json::Error> {
+        let identity_id = session_state.get("id")
+            .and_then(|value| serde_json::from_str::<String>(value).ok());
+        let dataset = SessionDataset {
+            identity_id,
+            state: serde_json::to_string(session_state)?,
+            ttl_seconds: ttl.whole_seconds(),
+        };
+        Ok(dataset)
+    }
+}
+
+impl SessionStore for PostgresSessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let connection = self.db.establish_connection().await
+            .map_err(|e| LoadError::Other(e.into()))?;
+        let repository = SessionRepository::new(&connection);
+        let state = repository.find_state(session_key.as_ref()).await
+            .map_err(|e| LoadError::Other(e.into()))?;
+
+        match state {
+            Some(state) => {
+                let session_state = serde_json::from_str(&state)
+                    .map_err(|e| LoadError::Deserialization(e.into()))?;
+                Ok(Some(session_state))
+            },
+            None => Ok(None),
+        }
+    }
+
+    async fn save(&self, session_state: SessionState, ttl: &Duration) -> Result<SessionKey, SaveError> {
+        let dataset = Self::create_dataset(&session_state, ttl)
+            .map_err(|e| SaveError::Serialization(e.into()))?;
+        let session_key = Self::generate_session_key();
+
+        let connection = self.db.establish_connection().await
+            .map_err(|e| SaveError::Other(e.into()))?;
+        let repository = SessionRepository::new(&connection);
+        repository.create(session_key.as_ref(), &dataset).await
+            .map_err(|e| SaveError::Other(e.into()))?;
+        Ok(session_key)
+    }
+
+    async fn update(&self, session_key: SessionKey, session_state: SessionState, ttl: &Duration) -> Result<SessionKey, UpdateError> {
+        let dataset = Self::create_dataset(&session_state, ttl)
+            .map_err(|e| UpdateError::Serialization(e.into()))?;
+
+        let connection = self.db.establish_connection().await
+            .map_err(|e| UpdateError::Other(e.into()))?;
+        let repository = SessionRepository::new(&connection);
+        let updated = repository.update(session_key.as_ref(), &dataset).await
+            .map_err(|e| UpdateError::Other(e.into()))?;
+        if updated {
+            return Ok(session_key)
+        }
+
+        let session_key = Self::generate_session_key();
+        repository.create(session_key.as_ref(), &dataset).await
+            .map_err(|e| UpdateError::Other(e.into()))?;
+        Ok(session_key)
+    }
+
+    async fn update_ttl(&self, session_key: &SessionKey, ttl: &Duration) -> Result<(), anyhow::Error> {
+        let connection = self.db.establish_connection().await?;
+        let repository = SessionRepository::new(&connection);
+        repository.touch(session_key.as_ref(), ttl.whole_seconds()).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        let connection = self.db.establish_connection().await?;
+        let repository = SessionRepository::new(&connection);
+        repository.delete_by_key(session_key.as_ref()).await?;
+        Ok(())
+    }
+}
+
+pub async fn sweep_expired_sessions(db: RepositoryAccess, period: StdDuration) {
+    let mut interval = tokio::time::interval(period);
+    loop {
+        interval.tick().await;
+        match delete_expired_sessions(&db).await {
+            Ok(0) => {},
+            Ok(count) => log::info!("Swept {} expired sessions", count),
+            Err(e) => log::warn!("Failed to sweep expired sessions: {}", e),
+        }
+    }
+}
+
+async fn delete_expired_sessions(db: &RepositoryAccess) -> Result<u64, DatabaseError> {
+    let connection = db.establish_connection().await?;
+    let repository = SessionRepository::new(&connection);
+    repository.delete_expired().await
+}
@@ -0,0 +1,143 @@
+use deadpool_postgres::Client;
+
+use super::DatabaseError;
+use super::connection::DatabaseConnection;
+
+type Result<T, E = DatabaseError> = std::result::Result<T, E>;
+
+pub struct TotpSecret {
+    pub secret: Vec<u8>,
+    pub confirmed: bool,
+    pub locked: bool,
+}
+
+pub struct TotpRepository<'a> {
+    client: &'a Client,
+}
+
+impl<'a> TotpRepository<'a> {
+    pub fn new(connection: &'a DatabaseConnection) -> Self {
+        Self {
+            client: connection,
+        }
+    }
+
+    pub async fn find(&self, identity_id: &str) -> Result<TotpSecret> {
+        let statement =
+            "select secret, confirmed_at is not null as confirmed,
+                coalesce(locked_until > now(), false) as locked
+                from totp_secrets
+                where identity_id = $1::varchar::uuid";
+        let row = self.client.query_opt(statement, &[&identity_id]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        let secret = TotpSecret {
+            secret: row.try_get("secret")?,
+            confirmed: row.try_get("confirmed")?,
+            locked: row.try_get("locked")?,
+        };
+        Ok(secret)
+    }
+
+    pub async fn is_enabled(&self, identity_id: &str) -> Result<bool> {
+        let statement =
+            "select exists (
+                select 1 from totp_secrets
+                  where identity_id = $1::varchar::uuid and confirmed_at is not null
+              ) as enabled";
+        let row = self.client.query_one(statement, &[&identity_id]).await?;
+        Ok(row.try_get("enabled")?)
+    }
+
+    /// Saves a secret awaiting confirmation, replacing an earlier
+    /// unconfirmed one. Returns `false` when TOTP is already enabled.
+    pub async fn begin(&self, identity_id: &str, secret: &[u8]) -> Result<bool> {
+        let statement =
+            "insert into totp_secrets (identity_id, secret)
+                values ($1::varchar::uuid, $2)
+                on conflict (identity_id) do update
+                  set secret = excluded.secret, last_used_step = null, created_at = now()
+                  where totp_secrets.confirmed_at is null";
+        let inserted = self.client.execute(statement, &[&identity_id, &secret]).await?;
+        Ok(inserted > 0)
+    }
+
+    pub async fn confirm(&self, identity_id: &str, step: i64, code_hashes: &[String]) -> Result<bool> {
+        let statement =
+            "with confirmed as (
+                  update totp_secrets set confirmed_at = now(), last_used_step = $2
+                    where identity_id = $1::varchar::uuid and confirmed_at is null
+                    returning identity_id
+                )
+                insert into totp_recovery_codes (id, identity_id, code_hash)
+                  select gen_random_uuid(), identity_id, code_hash
+                    from confirmed, unnest($3::varchar[]) as code_hash";
+        let inserted = self.client.execute(statement, &[&identity_id, &step, &code_hashes]).await?;
+        Ok(inserted > 0)
+    }
+
+    /// Records a code's time step, refusing steps at or before the last one
+    /// used so a code cannot be replayed.
+    pub async fn record_step(&self, identity_id: &str, step: i64) -> Result<bool> {
+        let statement =
+            "update totp_secrets set last_used_step = $2
+                where identity_id = $1::varchar::uuid and confirmed_at is not null
+                  and (last_used_step is null or last_used_step < $2)";
+        let updated = self.client.execute(statement, &[&identity_id, &step]).await?;
+        Ok(updated > 0)
+    }
+
+    /// Counts a rejected code. Reaching `limit` locks the second factor
+    /// for `lockout_seconds` and starts the count over.
+    pub async fn record_failure(&self, identity_id: &str, limit: i32, lockout_seconds: f64) -> Result<()> {
+        let statement =
+            "update totp_secrets
+                set failed_attempts = case when failed_attempts + 1 >= $2 then 0 else failed_attempts + 1 end,
+                    locked_until = case when failed_attempts + 1 >= $2
+                      then now() + make_interval(secs => $3) else locked_until end
+                where identity_id = $1::varchar::uuid";
+        self.client.execute(statement, &[&identity_id, &limit, &lockout_seconds]).await?;
+        Ok(())
+    }
+
+    pub async fn reset_failures(&self, identity_id: &str) -> Result<()> {
+        let statement =
+            "update totp_secrets set failed_attempts = 0
+                where identity_id = $1::varchar::uuid and failed_attempts > 0";
+        self.client.execute(statement, &[&identity_id]).await?;
+        Ok(())
+    }
+
+    pub async fn replace_recovery_codes(&self, identity_id: &str, code_hashes: &[String]) -> Result<()> {
+        let statement =
+            "with removed as (
+                  delete from totp_recovery_codes where identity_id = $1::varchar::uuid
+                )
+                insert into totp_recovery_codes (id, identity_id, code_hash)
+                  select gen_random_uuid(), $1::varchar::uuid, code_hash
+                    from unnest($2::varchar[]) as code_hash";
+        self.client.execute(statement, &[&identity_id, &code_hashes]).await?;
+        Ok(())
+    }
+
+    /// Consumes a recovery code. Returns `false` when it does not exist.
+    pub async fn use_recovery_code(&self, identity_id: &str, code_hash: &str) -> Result<bool> {
+        let statement =
+            "delete from totp_recovery_codes
+                where identity_id = $1::varchar::uuid and code_hash = $2";
+        let deleted = self.client.execute(statement, &[&identity_id, &code_hash]).await?;
+        Ok(deleted > 0)
+    }
+
+    pub async fn delete(&self, identity_id: &str) -> Result<()> {
+        let statement =
+            "with codes as (
+                  delete from totp_recovery_codes where identity_id = $1::varchar::uuid
+                )
+                delete from totp_secrets where identity_id = $1::varchar::uuid";
+        let deleted = self.client.execute(statement, &[&identity_id]).await?;
+        match deleted {
+            0 => Err(DatabaseError::NotFound),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use deadpool_postgres::Client;
+use serde_derive::{Deserialize, Serialize};
+use tokio_pg_mapper::FromTokioPostgresRow;
+use tokio_pg_mapper_derive::PostgresMapper;
+use tokio_postgres::Row;
+
+use super::DatabaseError;
+use super::connection::DatabaseConnection;
+
+type Result<T, E = DatabaseError> = std::result::Result<T, E>;
+
+#[derive(Deserialize, PostgresMapper, Serialize)]
+#[pg_mapper(table = "webauthn_credentials")]
+pub struct Passkey {
+    id: String,
+    pub credential_id: String,
+    name: String,
+    last_used_at: Option<String>,
+    created_at: String,
+}
+
+pub struct PasskeyDataset {
+    pub credential_id: String,
+    pub public_key: Vec<u8>,
+    pub algorithm: i32,
+    pub sign_count: i64,
+    pub name: String,
+}
+
+/// Everything needed to verify an assertion made with a stored passkey.
+pub struct StoredPasskey {
+    pub id: String,
+    pub identity_id: String,
+    pub public_key: Vec<u8>,
+    pub sign_count: i64,
+}
+
+const PASSKEY_COLUMNS: &str =
+    "cast(id as varchar) as id, credential_id, name,
+        to_char(last_used_at at time zone 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"') as last_used_at,
+        to_char(created_at at time zone 'UTC', 'YYYY-MM-DD\"T\"HH24:MI:SS.MS\"Z\"') as created_at";
+
+pub struct WebAuthnCredentialRepository<'a> {
+    client: &'a Client,
+}
+
+impl<'a> WebAuthnCredentialRepository<'a> {
+    pub fn new(connection: &'a DatabaseConnection) -> Self {
+        Self {
+            client: connection,
+        }
+    }
+
+    /// Stores a passkey for `identity_id`. Returns `None` when the credential
+    /// ID is already registered.
+    pub async fn create(&self, identity_id: &str, dataset: &PasskeyDataset) -> Result<Option<Passkey>> {
+        let statement = format!(
+            "insert into webauthn_credentials (id, identity_id, credential_id, public_key, algorithm, sign_count, name)
+                values (gen_random_uuid(), $1::varchar::uuid, $2, $3, $4, $5, $6)
+                on conflict (credential_id) do nothing
+                returning {}",
+            PASSKEY_COLUMNS);
+        let row = self.client.query_opt(statement.as_str(), &[
+            &identity_id, &dataset.credential_id, &dataset.public_key, &dataset.algorithm, &dataset.sign_count, &dataset.name,
+        ]).await?;
+        row.map(Passkey::try_from).transpose()
+    }
+
+    pub async fn list(&self, identity_id: &str) -> Result<Vec<Passkey>> {
+        let statement = format!(
+            "select {} from webauthn_credentials
+                where identity_id = $1::varchar::uuid
+                order by created_at",
+            PASSKEY_COLUMNS);
+        let rows = self.client.query(statement.as_str(), &[&identity_id]).await?;
+
+        let passkeys = rows.iter()
+            .map(Passkey::from_row_ref)
+            .collect::<Result<Vec<Passkey>, _>>()?;
+        Ok(passkeys)
+    }
+
+    pub async fn find_by_credential_id(&self, credential_id: &str) -> Result<StoredPasskey> {
+        let statement =
+            "select cast(id as varchar) as id, cast(identity_id as varchar) as identity_id, public_key, sign_count
+                from webauthn_credentials
+                where credential_id = $1";
+        let row = self.client.query_opt(statement, &[&credential_id]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        let passkey = StoredPasskey {
+            id: row.try_get("id")?,
+            identity_id: row.try_get("identity_id")?,
+            public_key: row.try_get("public_key")?,
+            sign_count: row.try_get("sign_count")?,
+        };
+        Ok(passkey)
+    }
+
+    /// Records a successful assertion. The update only applies while the
+    /// stored counter is still the one the assertion was checked against,
+    /// so two concurrent sign-ins cannot both advance it.
+    pub async fn record_use(&self, id: &str, previous_sign_count: i64, sign_count: i64) -> Result<()> {
+        let statement =
+            "update webauthn_credentials
+                set sign_count = $3, last_used_at = now()
+                where cast(id as varchar) = $1 and sign_count = $2";
+        let updated = self.client.execute(statement, &[&id, &previous_sign_count, &sign_count]).await?;
+        match updated {
+            0 => Err(DatabaseError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn delete(&self, identity_id: &str, id: &str) -> Result<Passkey> {
+        let statement = format!(
+            "delete from webauthn_credentials
+                where identity_id = $1::varchar::uuid and cast(id as varchar) = $2
+                returning {}",
+            PASSKEY_COLUMNS);
+        let row = self.client.query_opt(statement.as_str(), &[&identity_id, &id]).await?
+            .ok_or(DatabaseError::NotFound)?;
+        row.try_into()
+    }
+}
+
+impl TryFrom<Row> for Passkey {
+    type Error = DatabaseError;
+
+    fn try_from(value: Row) -> Result<Self, Self::Error> {
+        Ok(Self::from_row(value)?)
+    }
+}
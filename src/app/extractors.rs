@@ -0,0 +1,3 @@
+mod current_identity;
+
+pub use current_identity::{Credential, CurrentIdentity, CurrentIdentityError, record_sign_in};
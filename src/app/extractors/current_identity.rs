@@ -0,0 +1,306 @@
+use std::ops::Deref;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_session::{Session, SessionExt, SessionInsertError};
+use actix_web::dev::Payload;
+use actix_web::http::header;
+use actix_web::web::Data;
+use actix_web::{FromRequest, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+use futures_util::future::{FutureExt as _, LocalBoxFuture};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::app::context::Context;
+use crate::app::db::DatabaseError;
+use crate::app::db::access_token_repository::AccessTokenRepository;
+use crate::app::db::identity_repository::IdentityRepository;
+use crate::app::models::Identity;
+use crate::app::models::identity::Role;
+use crate::app::models::access_token::{TOKEN_PREFIX, hash_secret};
+use crate::app::models::scope::Scope;
+use crate::app::models::token_pair::verify_access_token;
+
+const SIGNED_IN_AT: &str = "signed-in-at";
+const LAST_ACTIVITY: &str = "last-activity";
+
+/// Starts the session clocks for a fresh sign-in. Call it after renewing
+/// the session and setting the identity.
+pub fn record_sign_in(session: &Session) -> Result<(), SessionInsertError> {
+    let now = unix_now();
+    session.insert(SIGNED_IN_AT, now)?;
+    session.insert(LAST_ACTIVITY, now)?;
+    Ok(())
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0)
+}
+
+#[derive(Debug, Error)]
+pub enum CurrentIdentityError {
+    #[error("Login required")]
+    LoginRequired,
+
+    #[error("Identity is deactivated")]
+    Deactivated,
+
+    #[error("Second factor required")]
+    SecondFactorRequired,
+
+    #[error("Session expired")]
+    SessionExpired,
+
+    #[error("Access token is invalid or expired")]
+    InvalidToken,
+
+    #[error("Session login required")]
+    SessionRequired,
+
+    #[error("Missing scope: {0}")]
+    ScopeMissing(Scope),
+
+    #[error("Missing role: {0}")]
+    RoleMissing(Role),
+
+    #[error("Failed to load session")]
+    SessionLoadingFailed,
+
+    #[error("Failed to save session")]
+    SessionSavingFailed,
+
+    #[error("Context is not configured")]
+    ContextMissing,
+
+    #[error("Database error: {source}")]
+    DatabaseError {
+        #[from]
+        source: DatabaseError,
+    },
+}
+
+impl ResponseError for CurrentIdentityError {
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            Self::LoginRequired => {
+                HttpResponse::Unauthorized().json(json!({
+                    "error": "login required",
+                }))
+            }
+            Self::Deactivated => {
+                HttpResponse::Unauthorized().json(json!({
+                    "error": "account deactivated",
+                }))
+            }
+            Self::SecondFactorRequired => {
+                HttpResponse::Unauthorized().json(json!({
+                    "error": "second factor required",
+                }))
+            }
+            Self::SessionExpired => {
+                HttpResponse::Unauthorized().json(json!({
+                    "error": "session expired",
+                }))
+            }
+            Self::InvalidToken => {
+                HttpResponse::Unauthorized().json(json!({
+                    "error": "invalid token",
+                }))
+            }
+            Self::SessionRequired => {
+                HttpResponse::Forbidden().json(json!({
+                    "error": "session login required",
+                }))
+            }
+            Self::ScopeMissing(scope) => {
+                HttpResponse::Forbidden()
+                    .insert_header((header::WWW_AUTHENTICATE, format!("Bearer error=\"insufficient_scope\", scope=\"{}\"", scope)))
+                    .json(json!({
+                        "error": "insufficient scope",
+                        "required_scope": scope,
+                    }))
+            }
+            Self::RoleMissing(role) => {
+                HttpResponse::Forbidden().json(json!({
+                    "error": "forbidden",
+                    "required_role": role,
+                }))
+            }
+            _ => {
+                HttpResponse::InternalServerError().json(json!({
+                    "error": "internal server error",
+                }))
+            }
+        }
+    }
+}
+
+/// How the current request proved its identity.
+#[derive(Clone)]
+pub enum Credential {
+    Session,
+    AccessToken {
+        scopes: Vec<Scope>,
+    },
+    /// A signed access token from token mode, standing in for a session.
+    Jwt,
+}
+
+#[derive(Clone)]
+pub struct CurrentIdentity {
+    identity: Identity,
+    credential: Credential,
+}
+
+impl CurrentIdentity {
+    pub fn credential(&self) -> &Credential {
+        &self.credential
+    }
+
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        match &self.credential {
+            Credential::Session | Credential::Jwt => true,
+            Credential::AccessToken { scopes } => scopes.contains(&scope),
+        }
+    }
+
+    /// Rejects requests authenticated by anything but a session cookie, for
+    /// operations a leaked token must not be able to perform.
+    pub fn require_session(&self) -> Result<(), CurrentIdentityError> {
+        match self.credential {
+            Credential::Session => Ok(()),
+            _ => Err(CurrentIdentityError::SessionRequired),
+        }
+    }
+
+    /// Rejects personal access tokens, which only carry the scopes they
+    /// were granted. Token-mode access tokens stand in for a full sign-in.
+    pub fn require_sign_in(&self) -> Result<(), CurrentIdentityError> {
+        match self.credential {
+            Credential::Session | Credential::Jwt => Ok(()),
+            Credential::AccessToken { .. } => Err(CurrentIdentityError::SessionRequired),
+        }
+    }
+
+    async fn load(req: &HttpRequest) -> Result<Self, CurrentIdentityError> {
+        let context = req.app_data::<Data<Context>>()
+            .ok_or(CurrentIdentityError::ContextMissing)?;
+        match Self::bearer_token(req) {
+            Some(token) if token.starts_with(TOKEN_PREFIX) => Self::load_from_token(context, token).await,
+            Some(token) => Self::load_from_jwt(context, token).await,
+            None => Self::load_from_session(context, req).await,
+        }
+    }
+
+    pub(crate) fn bearer_token(req: &HttpRequest) -> Option<&str> {
+        let authorization = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+        let (scheme, token) = authorization.split_once(' ')?;
+        scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+    }
+
+    async fn load_from_session(context: &Context, req: &HttpRequest) -> Result<Self, CurrentIdentityError> {
+        let session = req.get_session();
+        let id = match session.get::<String>("id").or(Err(CurrentIdentityError::SessionLoadingFailed))? {
+            Some(id) => id,
+            None if session.contains_key("pending-id") => return Err(CurrentIdentityError::SecondFactorRequired),
+            None => return Err(CurrentIdentityError::LoginRequired),
+        };
+        Self::track_activity(context, &session)?;
+
+        let identity = match Self::find_identity(context, &id).await {
+            Err(CurrentIdentityError::Deactivated) => {
+                session.purge();
+                return Err(CurrentIdentityError::Deactivated)
+            }
+            result => result?,
+        };
+        Ok(Self { identity, credential: Credential::Session })
+    }
+
+    /// Holds the session to the configured absolute and idle timeouts and
+    /// counts the request as activity.
+    fn track_activity(context: &Context, session: &Session) -> Result<(), CurrentIdentityError> {
+        let config = &context.config.session;
+        let signed_in_at = session.get::<i64>(SIGNED_IN_AT).or(Err(CurrentIdentityError::SessionLoadingFailed))?;
+        let last_activity = session.get::<i64>(LAST_ACTIVITY).or(Err(CurrentIdentityError::SessionLoadingFailed))?;
+
+        let now = unix_now();
+        let expired = signed_in_at.is_some_and(|at| now - at >= config.absolute_timeout_seconds)
+            || last_activity.is_some_and(|at| now - at >= config.idle_timeout_seconds);
+        if expired {
+            session.purge();
+            return Err(CurrentIdentityError::SessionExpired)
+        }
+
+        // Sessions from before the clocks existed start counting now.
+        if signed_in_at.is_none() {
+            session.insert(SIGNED_IN_AT, now)
+                .or(Err(CurrentIdentityError::SessionSavingFailed))?;
+        }
+        session.insert(LAST_ACTIVITY, now)
+            .or(Err(CurrentIdentityError::SessionSavingFailed))?;
+        Ok(())
+    }
+
+    async fn load_from_token(context: &Context, token: &str) -> Result<Self, CurrentIdentityError> {
+        let connection = context.db.establish_connection().await?;
+        let repository = AccessTokenRepository::new(&connection);
+        let grant = match repository.authenticate(&hash_secret(token)).await {
+            Ok(grant) => grant,
+            Err(DatabaseError::NotFound) => return Err(CurrentIdentityError::InvalidToken),
+            Err(e) => return Err(e.into()),
+        };
+
+        let identity = Self::find_identity(context, &grant.identity_id).await?;
+        let scopes = grant.scopes.iter()
+            .filter_map(|scope| scope.parse().ok())
+            .collect();
+        Ok(Self { identity, credential: Credential::AccessToken { scopes } })
+    }
+
+    async fn load_from_jwt(context: &Context, token: &str) -> Result<Self, CurrentIdentityError> {
+        let claims = verify_access_token(context, token).or(Err(CurrentIdentityError::InvalidToken))?;
+        let identity = Self::find_identity(context, &claims.sub).await?;
+        Ok(Self { identity, credential: Credential::Jwt })
+    }
+
+    async fn find_identity(context: &Context, id: &str) -> Result<Identity, CurrentIdentityError> {
+        let connection = context.db.establish_connection().await?;
+        let repository = IdentityRepository::new(&connection);
+        let identity = match repository.find_by_id(id).await {
+            Ok(identity) => identity,
+            Err(DatabaseError::NotFound) => return Err(CurrentIdentityError::LoginRequired),
+            Err(e) => return Err(e.into()),
+        };
+        if !identity.alive {
+            return Err(CurrentIdentityError::Deactivated)
+        }
+        Ok(identity)
+    }
+}
+
+impl Deref for CurrentIdentity {
+    type Target = Identity;
+
+    fn deref(&self) -> &Self::Target {
+        &self.identity
+    }
+}
+
+impl FromRequest for CurrentIdentity {
+    type Error = CurrentIdentityError;
+    type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let req = req.clone();
+        async move {
+            if let Some(current) = req.extensions().get::<CurrentIdentity>() {
+                return Ok(current.clone())
+            }
+
+            let current = Self::load(&req).await?;
+            req.extensions_mut().insert(current.clone());
+            Ok(current)
+        }
+        .boxed_local()
+    }
+}
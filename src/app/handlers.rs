@@ -1,15 +1,115 @@
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{Error, HttpRequest, HttpResponse, ResponseError};
+use actix_web::error::{JsonPayloadError, QueryPayloadError};
+use actix_web::web::{delete, get, post, resource, scope, JsonConfig, QueryConfig, ServiceConfig};
 use serde_json::json;
 
+use super::middlewares::{CsrfProtection, LoginRequired, RoleRequired};
 use super::models::DomainError;
+use super::models::identity::Role;
+use super::models::validation::ValidationErrors;
 
 pub mod root;
+mod access_tokens;
+mod admin;
 mod auth;
+mod credentials;
+pub mod profile;
 mod servant;
 pub mod sessions;
+mod token_pairs;
+mod totp;
+mod webauthn;
 
+pub use self::access_tokens::access_token_service_config;
+pub use self::admin::admin_service_config;
 pub use self::auth::auth_service_config;
+pub use self::credentials::credential_service_config;
 pub use self::servant::servant_service_config;
+pub use self::sessions::session_service_config;
+pub use self::totp::totp_service_config;
+pub use self::webauthn::webauthn_service_config;
+
+/// Every route sits behind CSRF protection; it only takes effect for
+/// mutating requests on signed-in sessions.
+pub fn app_config(config: &mut ServiceConfig) {
+    config
+        .app_data(JsonConfig::default().error_handler(json_error_handler))
+        .app_data(QueryConfig::default().error_handler(query_error_handler))
+        .service(
+            scope("")
+                .wrap(CsrfProtection::new())
+                .configure(routes)
+        );
+}
+
+fn routes(config: &mut ServiceConfig) {
+    config
+        .service(root::index)
+        .service(
+            scope("/auth/webauthn")
+                .configure(webauthn_service_config)
+        )
+        .service(
+            scope("/auth")
+                .configure(auth_service_config)
+        )
+        .service(
+            scope("/servants")
+                .wrap(LoginRequired::new())
+                .configure(servant_service_config)
+        )
+        .service(
+            resource("/me")
+                .wrap(LoginRequired::new())
+                .route(get().to(profile::show))
+                .route(delete().to(profile::deactivate))
+        )
+        .service(
+            scope("/me/credentials")
+                .wrap(LoginRequired::new())
+                .configure(credential_service_config)
+        )
+        .service(
+            scope("/me/totp")
+                .wrap(LoginRequired::new())
+                .configure(totp_service_config)
+        )
+        .service(
+            scope("/sessions")
+                .wrap(LoginRequired::new())
+                .configure(session_service_config)
+        )
+        .service(
+            scope("/tokens")
+                .wrap(LoginRequired::new())
+                .configure(access_token_service_config)
+        )
+        .service(
+            scope("/admin")
+                .wrap(RoleRequired::new(Role::Admin))
+                .wrap(LoginRequired::new())
+                .configure(admin_service_config)
+        )
+        .service(
+            resource("/signout")
+                .route(post().to(sessions::signout))
+        );
+}
+
+/// Bodies that do not deserialize get the same 422 as failed validation.
+fn json_error_handler(error: JsonPayloadError, _req: &HttpRequest) -> Error {
+    match error {
+        JsonPayloadError::Deserialize(e) => {
+            let code = if e.is_data() { "invalid_type" } else { "malformed" };
+            DomainError::from(ValidationErrors::single("body", code, e.to_string())).into()
+        }
+        _ => error.into(),
+    }
+}
+
+fn query_error_handler(error: QueryPayloadError, _req: &HttpRequest) -> Error {
+    DomainError::from(ValidationErrors::single("query", "malformed", error.to_string())).into()
+}
 
 impl ResponseError for DomainError {
     fn error_response(&self) -> HttpResponse {
@@ -21,6 +121,8 @@ impl DomainError {
     fn create_response(&self) -> HttpResponse {
         match self {
             Self::RecordNotFound => self.generic_not_found_response(),
+            Self::LastCredential => self.conflict_response(),
+            Self::ValidationFailed { source } => self.validation_failed_response(source),
             _ => self.generic_internal_server_error_response(),
         }
     }
@@ -32,6 +134,17 @@ impl DomainError {
         HttpResponse::NotFound().json(body)
     }
 
+    fn conflict_response(&self) -> HttpResponse {
+        let body = json!({
+            "error": self.to_string(),
+        });
+        HttpResponse::Conflict().json(body)
+    }
+
+    fn validation_failed_response(&self, errors: &ValidationErrors) -> HttpResponse {
+        HttpResponse::UnprocessableEntity().json(errors)
+    }
+
     fn generic_internal_server_error_response(&self) -> HttpResponse {
         let body = json!({
             "error": "internal server error",
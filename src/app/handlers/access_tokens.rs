@@ -0,0 +1,83 @@
+use actix_web::HttpResponse;
+use actix_web::web::{delete, get, post, Data, Json, Path, ServiceConfig};
+use serde_derive::Deserialize;
+use serde_json::json;
+
+use crate::app::context::Context;
+use crate::app::extractors::CurrentIdentity;
+use crate::app::models::DomainError;
+use crate::app::models::access_token::{AccessTokenIssuance, AccessTokenListing, AccessTokenRevocation, MAX_TTL_DAYS, NAME_MAX_LENGTH};
+use crate::app::models::scope::Scope;
+use crate::app::models::validation::{ChoiceRules, IntegerRules, TextRules, Validate, ValidationErrors, Validator};
+
+type Ctx = Data<Context>;
+type Result<T, E = actix_web::Error> = std::result::Result<T, E>;
+
+pub fn access_token_service_config(config: &mut ServiceConfig) {
+    config
+        .route("", get().to(list))
+        .route("", post().to(create))
+        .route("/{id}", delete().to(revoke));
+}
+
+#[derive(Deserialize)]
+struct CreateAccessTokenRequest {
+    name: Option<String>,
+    scopes: Option<Vec<String>>,
+    expires_in_days: Option<i64>,
+}
+
+impl Validate for CreateAccessTokenRequest {
+    type Output = (String, Vec<Scope>, Option<i64>);
+
+    fn validate(&self) -> std::result::Result<Self::Output, ValidationErrors> {
+        let mut validator = Validator::new();
+        let name_rules = TextRules::new().required().max_length(NAME_MAX_LENGTH);
+        let name = validator.text("name", self.name.as_deref(), &name_rules);
+
+        let scope_rules = ChoiceRules::new(Scope::allowed_values()).required();
+        let scopes: Option<Vec<Scope>> = match self.scopes.as_deref() {
+            Some([]) | None => {
+                validator.choice::<Scope>("scopes", None, &scope_rules);
+                None
+            }
+            Some(scopes) => scopes.iter()
+                .map(|scope| validator.choice("scopes", Some(scope), &scope_rules))
+                .collect(),
+        };
+
+        let ttl_rules = IntegerRules::new(1, MAX_TTL_DAYS);
+        let expires_in_days = self.expires_in_days.map(|days| days.to_string());
+        let ttl_days = validator.integer("expires_in_days", expires_in_days.as_deref(), &ttl_rules);
+
+        validator.finish(name.zip(scopes).map(|(name, scopes)| (name, scopes, ttl_days)))
+    }
+}
+
+async fn create(context: Ctx, identity: CurrentIdentity, request: Json<CreateAccessTokenRequest>) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let (name, scopes, ttl_days) = request.validate().map_err(DomainError::from)?;
+    let issuance = AccessTokenIssuance::new(&context, &identity.id, &name, scopes, ttl_days);
+    let token = issuance.execute().await?;
+    let response = HttpResponse::Created().json(token);
+    Ok(response)
+}
+
+async fn list(context: Ctx, identity: CurrentIdentity) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let listing = AccessTokenListing::new(&context, &identity.id);
+    let tokens = listing.execute().await?;
+    let response = HttpResponse::Ok().json(json!({
+        "tokens": tokens,
+    }));
+    Ok(response)
+}
+
+async fn revoke(context: Ctx, identity: CurrentIdentity, path: Path<String>) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let id = path.into_inner();
+    let revocation = AccessTokenRevocation::new(&context, &identity.id, &id);
+    let token = revocation.execute().await?;
+    let response = HttpResponse::Ok().json(token);
+    Ok(response)
+}
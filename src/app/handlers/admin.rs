@@ -0,0 +1,71 @@
+use actix_web::HttpResponse;
+use actix_web::web::{delete, get, patch, Data, Json, Path, ServiceConfig};
+use serde_derive::Deserialize;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::app::context::Context;
+use crate::app::models::DomainError;
+use crate::app::models::identity::{IdentityListing, IdentityStatusChange};
+use crate::app::models::servant::{ServantDeletion, ServantFetching};
+use crate::app::models::validation::{Validate, ValidationErrors, Validator};
+
+type Ctx = Data<Context>;
+type Result<T, E = actix_web::Error> = std::result::Result<T, E>;
+
+pub fn admin_service_config(config: &mut ServiceConfig) {
+    config
+        .route("/identities", get().to(list_identities))
+        .route("/identities/{id}", patch().to(update_identity))
+        .route("/servants/{id}", get().to(show_servant))
+        .route("/servants/{id}", delete().to(destroy_servant));
+}
+
+async fn list_identities(context: Ctx) -> Result<HttpResponse> {
+    let listing = IdentityListing::new(&context);
+    let identities = listing.execute().await?;
+    let response = HttpResponse::Ok().json(json!({
+        "identities": identities,
+    }));
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+struct UpdateIdentityRequest {
+    alive: Option<bool>,
+}
+
+impl Validate for UpdateIdentityRequest {
+    type Output = bool;
+
+    fn validate(&self) -> std::result::Result<Self::Output, ValidationErrors> {
+        let mut validator = Validator::new();
+        let alive = validator.present("alive", self.alive);
+        validator.finish(alive)
+    }
+}
+
+async fn update_identity(context: Ctx, path: Path<String>, request: Json<UpdateIdentityRequest>) -> Result<HttpResponse> {
+    let id = Uuid::parse_str(&path).or(Err(DomainError::RecordNotFound))?;
+    let alive = request.validate().map_err(DomainError::from)?;
+    let change = IdentityStatusChange::new(&context, &id.to_string(), alive);
+    let identity = change.execute().await?;
+    let response = HttpResponse::Ok().json(identity);
+    Ok(response)
+}
+
+async fn show_servant(context: Ctx, path: Path<i32>) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let fetching = ServantFetching::regardless_of_owner(&context, id);
+    let servant = fetching.execute().await?;
+    let response = HttpResponse::Ok().json(servant);
+    Ok(response)
+}
+
+async fn destroy_servant(context: Ctx, path: Path<i32>) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let deletion = ServantDeletion::regardless_of_owner(&context, id);
+    let servant = deletion.execute().await?;
+    let response = HttpResponse::Ok().json(servant);
+    Ok(response)
+}
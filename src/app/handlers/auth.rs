@@ -1,24 +1,107 @@
 use actix_session::Session;
 use actix_web::{HttpResponse, ResponseError};
-use actix_web::web::{Data, Form, ServiceConfig, delete, post};
+use actix_web::http::header;
+use actix_web::web::{Data, Form, Json, Path, Query, ServiceConfig, delete, get, post};
+use reqwest::Url;
+use serde_derive::{Deserialize, Serialize};
 use serde_json::json;
 
 use crate::app::context::Context;
+use crate::app::extractors::{CurrentIdentity, record_sign_in};
+use crate::app::middlewares::csrf_token;
 use crate::app::models::Identity;
-use crate::app::models::auth::{Authentication, AuthenticationError, AuthorizationRequest, CallbackParams};
+use crate::app::models::auth::{
+    Authentication, AuthenticationError, AuthorizationRequest, AuthenticationResult, CODE_CHALLENGE_METHOD, CallbackParams, IdentityProvider,
+    SavedAuthorization, find_provider, resolve_return_to,
+};
+use crate::app::models::token_pair::{TokenError, TokenPair, TokenPairIssuance};
+use crate::app::models::totp::{TotpError, TotpVerification, second_factor_required};
+use super::token_pairs;
+use super::totp::{CodeRequest, count_failed_attempt};
 
 type Result = std::result::Result<HttpResponse, AuthenticationError>;
 type Ctx = Data<Context>;
 
+/// Holds the identity of a sign-in that still has to pass TOTP. Only `id`
+/// counts as signed in, so nothing else honours a pending session.
+pub(super) const PENDING_ID: &str = "pending-id";
+const TOTP_ATTEMPTS: &str = "totp-attempts";
+/// Marks a pending sign-in that should end in a token pair, not a session.
+const PENDING_TOKEN_MODE: &str = "pending-token-mode";
+
+enum SignInState {
+    SignedIn,
+    SecondFactorPending,
+}
+
+/// How a completed sign-in is handed to the client: a session cookie, or a
+/// token pair for clients that cannot keep one.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ResponseMode {
+    #[default]
+    Session,
+    Token,
+}
+
+#[derive(Deserialize)]
+struct ResponseModeParams {
+    #[serde(default)]
+    mode: ResponseMode,
+}
+
+#[derive(Serialize)]
+struct SignInResponse {
+    identifier: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    second_factor_required: bool,
+    #[serde(flatten)]
+    tokens: Option<TokenPair>,
+}
+
 impl ResponseError for AuthenticationError {
     fn error_response(&self) -> HttpResponse {
         match *self {
-            AuthenticationError::StateMissing | AuthenticationError::StateNotMatch => {
+            AuthenticationError::StateMissing
+            | AuthenticationError::StateNotMatch
+            | AuthenticationError::NonceNotMatch
+            | AuthenticationError::ReturnToNotAllowed => {
                 HttpResponse::BadRequest().json(json!({
                     "status": "Bad Request",
                     "reason": self.to_string(),
                 }))
             }
+            AuthenticationError::UnknownProvider => {
+                HttpResponse::NotFound().json(json!({
+                    "status": "Not Found",
+                    "reason": self.to_string(),
+                }))
+            }
+            AuthenticationError::IdTokenMissing
+            | AuthenticationError::InvalidIdToken
+            | AuthenticationError::SigningKeyNotFound
+            | AuthenticationError::InvalidIdTokenSignature
+            | AuthenticationError::IssuerNotMatch
+            | AuthenticationError::AudienceNotMatch
+            | AuthenticationError::IdTokenExpired => {
+                HttpResponse::Unauthorized().json(json!({
+                    "status": "Unauthorized",
+                    "reason": self.to_string(),
+                }))
+            }
+            AuthenticationError::CredentialAlreadyLinked => {
+                HttpResponse::Conflict().json(json!({
+                    "status": "Conflict",
+                    "reason": self.to_string(),
+                }))
+            }
+            AuthenticationError::IdentityDeactivated => {
+                HttpResponse::Forbidden().json(json!({
+                    "status": "Forbidden",
+                    "reason": self.to_string(),
+                }))
+            }
             _ => {
                 HttpResponse::InternalServerError().json(json!({
                     "status": "internal server error",
@@ -31,56 +114,298 @@ impl ResponseError for AuthenticationError {
 
 pub fn auth_service_config(config: &mut ServiceConfig) {
     config
-        .route("", post().to(start))
-        .route("/callback", post().to(callback))
-        .route("/session", delete().to(signout));
+        .route("/session", delete().to(signout))
+        .route("/csrf", get().to(issue_csrf_token))
+        .route("/totp", post().to(verify_second_factor))
+        .route("/token/refresh", post().to(token_pairs::refresh))
+        .route("/token/revoke", post().to(token_pairs::revoke))
+        .route("/{provider}", post().to(start))
+        .route("/{provider}/login", get().to(login))
+        .route("/{provider}/link", post().to(start_link))
+        .route("/{provider}/link", get().to(login_link))
+        .route("/{provider}/callback", post().to(callback))
+        .route("/{provider}/callback", get().to(redirect_callback));
 }
 
-async fn start(context: Ctx, session: Session) -> Result {
-    let config = &context.config;
+async fn start(context: Ctx, session: Session, path: Path<String>) -> Result {
+    begin_with_json(&context, &session, &path, None).await
+}
+
+async fn start_link(context: Ctx, session: Session, path: Path<String>, identity: CurrentIdentity) -> actix_web::Result<HttpResponse> {
+    identity.require_session()?;
+    Ok(begin_with_json(&context, &session, &path, Some(&identity)).await?)
+}
+
+async fn begin_with_json(context: &Context, session: &Session, provider_name: &str, link: Option<&Identity>) -> Result {
+    let provider = find_provider(context, provider_name).await?;
+    let endpoints = provider.endpoints();
 
     let auth_request = AuthorizationRequest::new();
-    session.insert("auth-state", &auth_request.state)
-        .or(Err(AuthenticationError::StateSavingFailed))?;
+    save_authorization_request(session, provider.as_ref(), &auth_request, link)?;
 
     let response_json = json!({
-        "client_id": &config.auth.client_id,
-        "scope": "read:user",
+        "provider": provider.name(),
+        "authorize_url": &endpoints.authorize_url,
+        "client_id": &provider.config().client_id,
+        "redirect_uri": &provider.config().redirect_uri,
+        "scope": &endpoints.scope,
         "state": &auth_request.state,
+        "nonce": &auth_request.nonce,
+        "code_challenge": &auth_request.code_challenge,
+        "code_challenge_method": CODE_CHALLENGE_METHOD,
     });
     let response = HttpResponse::Ok().json(response_json);
     Ok(response)
 }
 
+#[derive(Deserialize)]
+struct LoginParams {
+    return_to: Option<String>,
+}
+
+async fn login(context: Ctx, session: Session, path: Path<String>, params: Query<LoginParams>) -> Result {
+    begin_with_redirect(&context, &session, &path, params.return_to.as_deref(), None).await
+}
+
+async fn login_link(context: Ctx, session: Session, path: Path<String>, params: Query<LoginParams>, identity: CurrentIdentity) -> actix_web::Result<HttpResponse> {
+    identity.require_session()?;
+    Ok(begin_with_redirect(&context, &session, &path, params.return_to.as_deref(), Some(&identity)).await?)
+}
+
+async fn begin_with_redirect(context: &Context, session: &Session, provider_name: &str, return_to: Option<&str>, link: Option<&Identity>) -> Result {
+    let provider = find_provider(context, provider_name).await?;
+    let return_to = resolve_return_to(&context.config.frontend, return_to)?;
+
+    let auth_request = AuthorizationRequest::new();
+    save_authorization_request(session, provider.as_ref(), &auth_request, link)?;
+    session.insert("auth-return-to", &return_to)
+        .or(Err(AuthenticationError::StateSavingFailed))?;
+
+    let response = HttpResponse::Found()
+        .insert_header((header::LOCATION, auth_request.authorize_url(provider.as_ref())?))
+        .finish();
+    Ok(response)
+}
+
 type Params = Form<CallbackParams>;
 
-async fn callback(context: Ctx, session: Session, params: Params) -> Result {
-    let key = "auth-state";
-    let saved_state: Option<String> =
-        session.get(key).or(Err(AuthenticationError::StateLoadingFailed))?;
-    let _ = session.remove(key);
+async fn callback(context: Ctx, session: Session, path: Path<String>, params: Params, mode: Query<ResponseModeParams>) -> actix_web::Result<HttpResponse> {
+    let token_mode = matches!(mode.mode, ResponseMode::Token);
+    if token_mode && context.config.token.is_none() {
+        return Err(TokenError::NotEnabled.into())
+    }
+    let provider = find_provider(&context, &path).await?;
+    let (auth_result, state) = complete_sign_in(&context, &session, provider.as_ref(), params.into_inner()).await?;
+
+    let second_factor_required = matches!(state, SignInState::SecondFactorPending);
+    let tokens = match (token_mode, second_factor_required) {
+        (true, false) => Some(issue_token_pair(&context, &session, &auth_result.identity.id).await?),
+        (true, true) => {
+            session.insert(PENDING_TOKEN_MODE, true)
+                .or(Err(AuthenticationError::StateSavingFailed))?;
+            None
+        }
+        (false, _) => None,
+    };
+    let response = HttpResponse::Ok().json(SignInResponse {
+        identifier: auth_result.identity.id,
+        name: Some(auth_result.name),
+        second_factor_required,
+        tokens,
+    });
+    Ok(response)
+}
+
+/// Ends the sign-in in token mode: the client gets a token pair and the
+/// session used for the handshake is dropped.
+async fn issue_token_pair(context: &Context, session: &Session, identity_id: &str) -> std::result::Result<TokenPair, TokenError> {
+    let issuance = TokenPairIssuance::new(context, identity_id);
+    let pair = issuance.execute().await?;
+    session.purge();
+    Ok(pair)
+}
+
+/// The provider sends `error` instead of `code` when the user declines, so
+/// every field is optional here.
+#[derive(Deserialize)]
+struct RedirectCallbackParams {
+    state: Option<String>,
+    code: Option<String>,
+    error: Option<String>,
+}
+
+/// Ends a redirect-based sign-in back on the frontend. The outcome travels
+/// in the query: `second_factor=required` while TOTP is pending, or `error`
+/// when the sign-in failed.
+async fn redirect_callback(context: Ctx, session: Session, path: Path<String>, params: Query<RedirectCallbackParams>) -> Result {
+    let return_to = take_from_session(&session, "auth-return-to")?
+        .unwrap_or_else(|| context.config.frontend.base_uri.clone());
+    let params = params.into_inner();
+
+    let outcome = match (params.error, params.state, params.code) {
+        (Some(_), _, _) => Err("access_denied"),
+        (None, Some(state), Some(code)) => {
+            let params = CallbackParams {
+                state,
+                code,
+            };
+            let result = match find_provider(&context, &path).await {
+                Ok(provider) => complete_sign_in(&context, &session, provider.as_ref(), params).await,
+                Err(e) => Err(e),
+            };
+            result.map(|(_, state)| state).map_err(|e| redirect_error(&e))
+        }
+        _ => Err("invalid_request"),
+    };
 
-    let auth = Authentication::new(&context, params.into_inner(), saved_state);
+    let query = match outcome {
+        Ok(SignInState::SignedIn) => None,
+        Ok(SignInState::SecondFactorPending) => Some(("second_factor", "required")),
+        Err(error) => Some(("error", error)),
+    };
+    let location = match (query, Url::parse(&return_to)) {
+        (Some((key, value)), Ok(mut url)) => {
+            url.query_pairs_mut().append_pair(key, value);
+            url.into()
+        }
+        _ => return_to,
+    };
+    let response = HttpResponse::Found()
+        .insert_header((header::LOCATION, location))
+        .finish();
+    Ok(response)
+}
+
+/// The `error` value a redirect-based sign-in reports to the frontend.
+fn redirect_error(error: &AuthenticationError) -> &'static str {
+    match *error {
+        AuthenticationError::StateMissing
+        | AuthenticationError::StateNotMatch
+        | AuthenticationError::NonceNotMatch => "invalid_state",
+        AuthenticationError::UnknownProvider => "unknown_provider",
+        AuthenticationError::IdTokenMissing
+        | AuthenticationError::InvalidIdToken
+        | AuthenticationError::SigningKeyNotFound
+        | AuthenticationError::InvalidIdTokenSignature
+        | AuthenticationError::IssuerNotMatch
+        | AuthenticationError::AudienceNotMatch
+        | AuthenticationError::IdTokenExpired => "invalid_id_token",
+        AuthenticationError::CredentialAlreadyLinked => "credential_already_linked",
+        AuthenticationError::IdentityDeactivated => "identity_deactivated",
+        _ => "server_error",
+    }
+}
+
+fn save_authorization_request(session: &Session, provider: &dyn IdentityProvider, auth_request: &AuthorizationRequest, link: Option<&Identity>) -> std::result::Result<(), AuthenticationError> {
+    let _ = session.remove("auth-return-to");
+    let _ = session.remove("auth-link");
+    if let Some(identity) = link {
+        session.insert("auth-link", &identity.id)
+            .or(Err(AuthenticationError::StateSavingFailed))?;
+    }
+    session.insert("auth-state", &auth_request.state)
+        .or(Err(AuthenticationError::StateSavingFailed))?;
+    session.insert("auth-nonce", &auth_request.nonce)
+        .or(Err(AuthenticationError::StateSavingFailed))?;
+    session.insert("auth-code-verifier", &auth_request.code_verifier)
+        .or(Err(AuthenticationError::StateSavingFailed))?;
+    session.insert("auth-provider", provider.name())
+        .or(Err(AuthenticationError::StateSavingFailed))?;
+    Ok(())
+}
+
+async fn complete_sign_in(context: &Context, session: &Session, provider: &dyn IdentityProvider, params: CallbackParams) -> std::result::Result<(AuthenticationResult, SignInState), AuthenticationError> {
+    let saved = SavedAuthorization {
+        state: take_from_session(session, "auth-state")?,
+        nonce: take_from_session(session, "auth-nonce")?,
+        code_verifier: take_from_session(session, "auth-code-verifier")?,
+        link_identity_id: take_from_session(session, "auth-link")?,
+    };
+    let saved_provider = take_from_session(session, "auth-provider")?;
+    if saved_provider.is_some_and(|saved| saved != provider.name()) {
+        return Err(AuthenticationError::StateNotMatch)
+    }
+    if let Some(link_identity_id) = &saved.link_identity_id {
+        let current_id: Option<String> = session.get("id").or(Err(AuthenticationError::StateLoadingFailed))?;
+        if current_id.as_ref() != Some(link_identity_id) {
+            return Err(AuthenticationError::StateNotMatch)
+        }
+    }
+
+    // Linking happens within a session that already passed its second factor.
+    let linking = saved.link_identity_id.is_some();
+    let auth = Authentication::new(context, provider, params, saved);
     let auth_result = auth.execute().await?;
+    let second_factor = !linking && second_factor_required(context, &auth_result.identity.id).await
+        .or(Err(AuthenticationError::DatabaseConnectionFailed))?;
 
     session.clear();
     session.renew();
-    set_identity_to_session(&session, &auth_result.identity)?;
+    if second_factor {
+        session.insert(PENDING_ID, &auth_result.identity.id)
+            .or(Err(AuthenticationError::TokenSavingFailed))?;
+        return Ok((auth_result, SignInState::SecondFactorPending))
+    }
+    set_identity_to_session(session, &auth_result.identity)?;
+    Ok((auth_result, SignInState::SignedIn))
+}
 
-    let json = json!({
-        "identifier": auth_result.identity.id,
-        "name": auth_result.name,
+async fn verify_second_factor(context: Ctx, session: Session, request: Json<CodeRequest>) -> actix_web::Result<HttpResponse> {
+    let identity_id: String = session.get(PENDING_ID)
+        .or(Err(TotpError::StateLoadingFailed))?
+        .ok_or(TotpError::NotPending)?;
+
+    let verification = TotpVerification::new(&context, &identity_id, &request.code);
+    verification.execute().await
+        .map_err(|error| count_failed_attempt(&session, TOTP_ATTEMPTS, error))?;
+
+    let token_mode = session.get::<bool>(PENDING_TOKEN_MODE).unwrap_or_default().unwrap_or(false);
+    let tokens = match token_mode {
+        true => Some(issue_token_pair(&context, &session, &identity_id).await?),
+        false => {
+            session.clear();
+            session.renew();
+            session.insert("id", &identity_id)
+                .or(Err(TotpError::StateSavingFailed))?;
+            record_sign_in(&session)
+                .or(Err(TotpError::StateSavingFailed))?;
+            None
+        }
+    };
+
+    let response = HttpResponse::Ok().json(SignInResponse {
+        identifier: identity_id,
+        name: None,
+        second_factor_required: false,
+        tokens,
     });
-    let response = HttpResponse::Ok().json(json);
     Ok(response)
 }
 
+fn take_from_session(session: &Session, key: &str) -> std::result::Result<Option<String>, AuthenticationError> {
+    let value = session.get(key).or(Err(AuthenticationError::StateLoadingFailed))?;
+    let _ = session.remove(key);
+    Ok(value)
+}
+
 fn set_identity_to_session(session: &Session, identity: &Identity) -> std::result::Result<(), AuthenticationError> {
     session.insert("id", &identity.id)
         .or(Err(AuthenticationError::TokenSavingFailed))?;
+    record_sign_in(session)
+        .or(Err(AuthenticationError::TokenSavingFailed))?;
     Ok(())
 }
 
+/// Hands out the token to echo in `X-CSRF-Token`. Signing in starts a new
+/// session, so clients fetch it again afterwards.
+async fn issue_csrf_token(session: Session) -> actix_web::Result<HttpResponse> {
+    let token = csrf_token(&session)?;
+    let response = HttpResponse::Ok().json(json!({
+        "csrf_token": token,
+    }));
+    Ok(response)
+}
+
 async fn signout(session: Session) -> Result {
     session.purge();
 
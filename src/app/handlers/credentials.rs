@@ -0,0 +1,35 @@
+use actix_web::HttpResponse;
+use actix_web::web::{delete, get, Data, Path, ServiceConfig};
+use serde_json::json;
+
+use crate::app::context::Context;
+use crate::app::extractors::CurrentIdentity;
+use crate::app::models::credential::{CredentialListing, CredentialUnlinking};
+
+type Ctx = Data<Context>;
+type Result<T, E = actix_web::Error> = std::result::Result<T, E>;
+
+pub fn credential_service_config(config: &mut ServiceConfig) {
+    config
+        .route("", get().to(list))
+        .route("/{id}", delete().to(unlink));
+}
+
+async fn list(context: Ctx, identity: CurrentIdentity) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let listing = CredentialListing::new(&context, &identity.id);
+    let credentials = listing.execute().await?;
+    let response = HttpResponse::Ok().json(json!({
+        "credentials": credentials,
+    }));
+    Ok(response)
+}
+
+async fn unlink(context: Ctx, identity: CurrentIdentity, path: Path<String>) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let id = path.into_inner();
+    let unlinking = CredentialUnlinking::new(&context, &identity.id, &id);
+    let credential = unlinking.execute().await?;
+    let response = HttpResponse::Ok().json(credential);
+    Ok(response)
+}
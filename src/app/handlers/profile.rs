@@ -0,0 +1,35 @@
+use actix_session::Session;
+use actix_web::HttpResponse;
+use actix_web::web::Data;
+use serde_json::json;
+
+use crate::app::context::Context;
+use crate::app::extractors::CurrentIdentity;
+use crate::app::models::identity::IdentityDeactivation;
+
+type Ctx = Data<Context>;
+type Result<T, E = actix_web::Error> = std::result::Result<T, E>;
+
+pub async fn show(identity: CurrentIdentity) -> Result<HttpResponse> {
+    identity.require_sign_in()?;
+    let response = HttpResponse::Ok().json(json!({
+        "id": identity.id,
+        "login": identity.login,
+        "role": identity.role,
+        "name": identity.name,
+        "avatar_url": identity.avatar_url,
+    }));
+    Ok(response)
+}
+
+pub async fn deactivate(context: Ctx, session: Session, identity: CurrentIdentity) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let deactivation = IdentityDeactivation::new(&context, &identity.id);
+    deactivation.execute().await?;
+    session.purge();
+
+    let response = HttpResponse::Ok().json(json!({
+        "status": "ok",
+    }));
+    Ok(response)
+}
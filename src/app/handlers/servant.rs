@@ -1,57 +1,239 @@
-use actix_web::web::{delete, get, post, Data, Json, Path, ServiceConfig};
-use actix_web::HttpResponse;
+use actix_web::web::{delete, get, patch, post, put, Data, Json, Path, Query, ServiceConfig};
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse};
 use serde_derive::Deserialize;
 use serde_json::json;
 
 use crate::app::context::Context;
-use crate::app::models::servant::{ServantDeletion, ServantFetching, ServantListing, ServantRegistration};
+use crate::app::extractors::CurrentIdentity;
+use crate::app::middlewares::ScopeRequired;
+use crate::app::models::DomainError;
+use crate::app::models::scope::Scope;
+use crate::app::models::servant::{NAME_MAX_LENGTH, ServantClass, ServantCursor, ServantDeletion, ServantFetching, ServantListing, ServantRegistration, ServantUpdate};
+use crate::app::models::servant::listing::{DEFAULT_LIMIT, MAX_LIMIT, ServantFilter, ServantSort, SortColumn, SortOrder};
+use crate::app::models::validation::{ChoiceRules, IntegerRules, TextRules, Validate, ValidationErrors, Validator};
 
 type Ctx = Data<Context>;
 type Result<T, E = actix_web::Error> = std::result::Result<T, E>;
 
 pub fn servant_service_config(config: &mut ServiceConfig) {
     config
-        .route("", get().to(list))
-        .route("", post().to(create))
-        .route("/{id}", get().to(show))
-        .route("/{id}", delete().to(destroy));
+        .route("", get().to(list).wrap(ScopeRequired::new(Scope::ServantsRead)))
+        .route("", post().to(create).wrap(ScopeRequired::new(Scope::ServantsWrite)))
+        .route("/{id}", get().to(show).wrap(ScopeRequired::new(Scope::ServantsRead)))
+        .route("/{id}", put().to(replace).wrap(ScopeRequired::new(Scope::ServantsWrite)))
+        .route("/{id}", patch().to(update).wrap(ScopeRequired::new(Scope::ServantsWrite)))
+        .route("/{id}", delete().to(destroy).wrap(ScopeRequired::new(Scope::ServantsWrite)));
 }
 
 #[derive(Deserialize)]
 struct CreateServantRequest {
-    name: String,
-    class_name: String,
+    name: Option<String>,
+    class_name: Option<String>,
 }
 
-async fn create(context: Ctx, request: Json<CreateServantRequest>) -> Result<HttpResponse> {
-    let registration = ServantRegistration::new(&context, &request.name, &request.class_name);
+impl Validate for CreateServantRequest {
+    type Output = (String, ServantClass);
+
+    fn validate(&self) -> std::result::Result<Self::Output, ValidationErrors> {
+        let mut validator = Validator::new();
+        let name_rules = TextRules::new().required().max_length(NAME_MAX_LENGTH);
+        let class_rules = ChoiceRules::new(ServantClass::allowed_values()).required();
+        let name = validator.text("name", self.name.as_deref(), &name_rules);
+        let class_name = validator.choice("class_name", self.class_name.as_deref(), &class_rules);
+        validator.finish(name.zip(class_name))
+    }
+}
+
+async fn create(context: Ctx, identity: CurrentIdentity, request: Json<CreateServantRequest>) -> Result<HttpResponse> {
+    let (name, class_name) = request.validate().map_err(DomainError::from)?;
+    let registration = ServantRegistration::new(&context, &identity.id, &name, class_name);
     let servant = registration.execute().await?;
     let response = HttpResponse::Created().json(servant);
     Ok(response)
 }
 
-async fn list(context: Ctx) -> Result<HttpResponse> {
-    let listing = ServantListing::new(&context);
-    let servants = listing.execute().await?;
+#[derive(Deserialize)]
+struct UpdateServantRequest {
+    name: Option<String>,
+    class_name: Option<String>,
+}
+
+impl Validate for UpdateServantRequest {
+    type Output = (Option<String>, Option<ServantClass>);
+
+    fn validate(&self) -> std::result::Result<Self::Output, ValidationErrors> {
+        let mut validator = Validator::new();
+        let name_rules = TextRules::new().max_length(NAME_MAX_LENGTH);
+        let class_rules = ChoiceRules::new(ServantClass::allowed_values());
+        let name = validator.text("name", self.name.as_deref(), &name_rules);
+        let class_name = validator.choice("class_name", self.class_name.as_deref(), &class_rules);
+        validator.finish(Some((name, class_name)))
+    }
+}
+
+type ReplaceServantRequest = CreateServantRequest;
+
+async fn replace(context: Ctx, identity: CurrentIdentity, path: Path<i32>, request: Json<ReplaceServantRequest>) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let (name, class_name) = request.validate().map_err(DomainError::from)?;
+    let update = ServantUpdate::new(&context, &identity.id, id, Some(&name), Some(class_name));
+    let servant = update.execute().await?;
+    let response = HttpResponse::Ok().json(servant);
+    Ok(response)
+}
+
+async fn update(context: Ctx, identity: CurrentIdentity, path: Path<i32>, request: Json<UpdateServantRequest>) -> Result<HttpResponse> {
+    let id = path.into_inner();
+    let (name, class_name) = request.validate().map_err(DomainError::from)?;
+    let update = ServantUpdate::new(&context, &identity.id, id, name.as_deref(), class_name);
+    let servant = update.execute().await?;
+    let response = HttpResponse::Ok().json(servant);
+    Ok(response)
+}
+
+#[derive(Default)]
+struct ListServantsRequest {
+    classes: Vec<String>,
+    q: Option<String>,
+    sort: Option<String>,
+    order: Option<String>,
+    limit: Option<String>,
+    after: Option<String>,
+}
+
+impl ListServantsRequest {
+    fn from_pairs(pairs: Vec<(String, String)>) -> Self {
+        let mut request = Self::default();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "class" => request.classes.extend(value.split(',').map(|class| class.to_owned())),
+                "q" => request.q = Some(value).filter(|q| !q.trim().is_empty()),
+                "sort" => request.sort = Some(value),
+                "order" => request.order = Some(value),
+                "limit" => request.limit = Some(value),
+                "after" => request.after = Some(value),
+                _ => {},
+            }
+        }
+        request
+    }
+}
+
+impl Validate for ListServantsRequest {
+    type Output = (ServantFilter, ServantSort, i64, Option<ServantCursor>);
+
+    fn validate(&self) -> std::result::Result<Self::Output, ValidationErrors> {
+        let mut validator = Validator::new();
+
+        let class_rules = ChoiceRules::new(ServantClass::allowed_values());
+        let classes = self.classes.iter()
+            .filter_map(|class| validator.choice("class", Some(class), &class_rules))
+            .collect();
+        let q_rules = TextRules::new().max_length(NAME_MAX_LENGTH);
+        let name_query = validator.text("q", self.q.as_deref(), &q_rules);
+        let filter = ServantFilter {
+            classes,
+            name_query,
+        };
+
+        let default_sort = ServantSort::default();
+        let sort_rules = ChoiceRules::new(SortColumn::allowed_values());
+        let order_rules = ChoiceRules::new(SortOrder::allowed_values());
+        let sort = ServantSort {
+            column: validator.choice("sort", self.sort.as_deref(), &sort_rules).unwrap_or(default_sort.column),
+            order: validator.choice("order", self.order.as_deref(), &order_rules).unwrap_or(default_sort.order),
+        };
+
+        let limit_rules = IntegerRules::new(1, MAX_LIMIT);
+        let limit = validator.integer("limit", self.limit.as_deref(), &limit_rules);
+        let after: Option<ServantCursor> = validator.parsed("after", self.after.as_deref());
+        if after.as_ref().is_some_and(|cursor| !cursor.matches(&sort)) {
+            validator.invalid("after", "sort_mismatch", "after belongs to a different sort or order".to_owned());
+        }
+        validator.finish(Some((filter, sort, limit.unwrap_or(DEFAULT_LIMIT), after)))
+    }
+}
+
+async fn list(context: Ctx, identity: CurrentIdentity, req: HttpRequest, query: Query<Vec<(String, String)>>) -> Result<HttpResponse> {
+    let request = ListServantsRequest::from_pairs(query.into_inner());
+    let (filter, sort, limit, after) = request.validate().map_err(DomainError::from)?;
+    let listing = ServantListing::new(&context, &identity.id, filter, sort, limit, after);
+    let page = listing.execute().await?;
+
+    let next_cursor = page.next_cursor.map(|cursor| cursor.encode());
+    let mut response = HttpResponse::Ok();
+    if let Some(cursor) = &next_cursor {
+        response.insert_header((header::LINK, next_page_link(&req, cursor)));
+    }
     let response_json = json!({
-        "servants": servants,
+        "servants": page.servants,
+        "next_cursor": next_cursor,
     });
-    let response = HttpResponse::Ok().json(response_json);
-    Ok(response)
+    Ok(response.json(response_json))
+}
+
+fn next_page_link(req: &HttpRequest, cursor: &str) -> String {
+    let mut pairs = req.query_string()
+        .split('&')
+        .filter(|pair| !pair.is_empty() && !pair.starts_with("after="))
+        .collect::<Vec<&str>>();
+    let after = format!("after={}", cursor);
+    pairs.push(&after);
+    format!("<{}?{}>; rel=\"next\"", req.path(), pairs.join("&"))
 }
 
-async fn show(context: Ctx, path: Path<i32>) -> Result<HttpResponse> {
+async fn show(context: Ctx, identity: CurrentIdentity, path: Path<i32>) -> Result<HttpResponse> {
     let id = path.into_inner();
-    let fetching = ServantFetching::new(&context, id);
+    let fetching = ServantFetching::new(&context, &identity.id, id);
     let servant = fetching.execute().await?;
     let response = HttpResponse::Ok().json(servant);
     Ok(response)
 }
 
-async fn destroy(context: Ctx, path: Path<i32>) -> Result<HttpResponse> {
+async fn destroy(context: Ctx, identity: CurrentIdentity, path: Path<i32>) -> Result<HttpResponse> {
     let id = path.into_inner();
-    let deletion = ServantDeletion::new(&context, id);
+    let deletion = ServantDeletion::new(&context, &identity.id, id);
     let servant = deletion.execute().await?;
     let response = HttpResponse::Ok().json(servant);
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+
+    use super::*;
+
+    #[test]
+    fn next_page_link_replaces_the_cursor_and_keeps_other_parameters() {
+        let req = TestRequest::with_uri("/servants?class=saber&after=old&limit=2").to_http_request();
+        assert_eq!(next_page_link(&req, "next"), "</servants?class=saber&limit=2&after=next>; rel=\"next\"");
+    }
+
+    #[test]
+    fn rejects_cursors_from_another_sort() {
+        let issued = ListServantsRequest {
+            after: Some(URL_SAFE_NO_PAD.encode(r#"{"id":3,"key":"Mash","sort":"name","order":"asc"}"#)),
+            sort: Some("name".to_owned()),
+            ..Default::default()
+        };
+        assert!(issued.validate().is_ok());
+
+        let resorted = ListServantsRequest {
+            sort: Some("class_name".to_owned()),
+            ..issued
+        };
+        let errors = resorted.validate().err().unwrap();
+        assert_eq!(errors.errors.len(), 1);
+        assert_eq!(errors.errors[0].field, "after");
+        assert_eq!(errors.errors[0].code, "sort_mismatch");
+    }
+
+    #[test]
+    fn next_page_link_without_a_query() {
+        let req = TestRequest::with_uri("/servants").to_http_request();
+        assert_eq!(next_page_link(&req, "next"), "</servants?after=next>; rel=\"next\"");
+    }
+}
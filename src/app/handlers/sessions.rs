@@ -1,9 +1,22 @@
 use actix_session::Session;
 use actix_web::HttpResponse;
+use actix_web::web::{delete, get, Data, Path, ServiceConfig};
 use serde_json::json;
 
+use crate::app::context::Context;
+use crate::app::extractors::CurrentIdentity;
+use crate::app::models::session::{SessionListing, SessionRevocation, SessionRevocationAll};
+
+type Ctx = Data<Context>;
 type Result<T, E = actix_web::Error> = std::result::Result<T, E>;
 
+pub fn session_service_config(config: &mut ServiceConfig) {
+    config
+        .route("", get().to(list))
+        .route("", delete().to(revoke_all))
+        .route("/{id}", delete().to(revoke));
+}
+
 pub async fn signout(session: Session) -> Result<HttpResponse> {
     session.purge();
     let response = HttpResponse::Ok().json(json!({
@@ -11,3 +24,35 @@ pub async fn signout(session: Session) -> Result<HttpResponse> {
     }));
     Ok(response)
 }
+
+async fn list(context: Ctx, identity: CurrentIdentity) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let listing = SessionListing::new(&context, &identity.id);
+    let sessions = listing.execute().await?;
+    let response = HttpResponse::Ok().json(json!({
+        "sessions": sessions,
+    }));
+    Ok(response)
+}
+
+async fn revoke(context: Ctx, identity: CurrentIdentity, path: Path<String>) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let id = path.into_inner();
+    let revocation = SessionRevocation::new(&context, &identity.id, &id);
+    let session = revocation.execute().await?;
+    let response = HttpResponse::Ok().json(session);
+    Ok(response)
+}
+
+async fn revoke_all(context: Ctx, session: Session, identity: CurrentIdentity) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let revocation = SessionRevocationAll::new(&context, &identity.id);
+    let count = revocation.execute().await?;
+    session.purge();
+
+    let response = HttpResponse::Ok().json(json!({
+        "status": "ok",
+        "revoked": count,
+    }));
+    Ok(response)
+}
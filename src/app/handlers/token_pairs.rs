@@ -0,0 +1,65 @@
+use actix_web::{HttpResponse, ResponseError};
+use actix_web::web::{Data, Json};
+use serde_derive::Deserialize;
+use serde_json::json;
+
+use crate::app::context::Context;
+use crate::app::models::token_pair::{TokenError, TokenPairRefresh, TokenPairRevocation};
+
+type Ctx = Data<Context>;
+type Result<T, E = TokenError> = std::result::Result<T, E>;
+
+impl ResponseError for TokenError {
+    fn error_response(&self) -> HttpResponse {
+        match *self {
+            TokenError::NotEnabled => {
+                HttpResponse::NotFound().json(json!({
+                    "status": "Not Found",
+                    "reason": self.to_string(),
+                }))
+            }
+            TokenError::InvalidAccessToken
+            | TokenError::AccessTokenExpired
+            | TokenError::InvalidRefreshToken
+            | TokenError::RefreshTokenReused => {
+                HttpResponse::Unauthorized().json(json!({
+                    "status": "Unauthorized",
+                    "reason": self.to_string(),
+                }))
+            }
+            TokenError::IdentityDeactivated => {
+                HttpResponse::Forbidden().json(json!({
+                    "status": "Forbidden",
+                    "reason": self.to_string(),
+                }))
+            }
+            _ => {
+                HttpResponse::InternalServerError().json(json!({
+                    "status": "internal server error",
+                    "reason": self.to_string(),
+                }))
+            }
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RefreshTokenRequest {
+    refresh_token: String,
+}
+
+pub async fn refresh(context: Ctx, request: Json<RefreshTokenRequest>) -> Result<HttpResponse> {
+    let refresh = TokenPairRefresh::new(&context, &request.refresh_token);
+    let pair = refresh.execute().await?;
+    let response = HttpResponse::Ok().json(pair);
+    Ok(response)
+}
+
+pub async fn revoke(context: Ctx, request: Json<RefreshTokenRequest>) -> Result<HttpResponse> {
+    let revocation = TokenPairRevocation::new(&context, &request.refresh_token);
+    revocation.execute().await?;
+    let response = HttpResponse::Ok().json(json!({
+        "result": "ok",
+    }));
+    Ok(response)
+}
@@ -0,0 +1,136 @@
+use actix_session::Session;
+use actix_web::{HttpResponse, ResponseError};
+use actix_web::web::{delete, get, post, Data, Json, ServiceConfig};
+use serde_derive::Deserialize;
+use serde_json::json;
+
+use crate::app::context::Context;
+use crate::app::extractors::CurrentIdentity;
+use crate::app::models::totp::{RecoveryCodeRegeneration, TotpConfirmation, TotpDeactivation, TotpEnrolment, TotpError, MAX_ATTEMPTS, second_factor_required};
+
+type Ctx = Data<Context>;
+type Result<T, E = actix_web::Error> = std::result::Result<T, E>;
+
+/// Failed codes on the endpoints that manage TOTP; too many sign the
+/// session out.
+const MANAGE_ATTEMPTS: &str = "totp-manage-attempts";
+
+impl ResponseError for TotpError {
+    fn error_response(&self) -> HttpResponse {
+        match *self {
+            TotpError::NotPending => {
+                HttpResponse::BadRequest().json(json!({
+                    "status": "Bad Request",
+                    "reason": self.to_string(),
+                }))
+            }
+            TotpError::InvalidCode => {
+                HttpResponse::Unauthorized().json(json!({
+                    "status": "Unauthorized",
+                    "reason": self.to_string(),
+                }))
+            }
+            TotpError::NotEnrolled => {
+                HttpResponse::NotFound().json(json!({
+                    "status": "Not Found",
+                    "reason": self.to_string(),
+                }))
+            }
+            TotpError::AlreadyEnabled => {
+                HttpResponse::Conflict().json(json!({
+                    "status": "Conflict",
+                    "reason": self.to_string(),
+                }))
+            }
+            TotpError::TooManyAttempts => {
+                HttpResponse::TooManyRequests().json(json!({
+                    "status": "Too Many Requests",
+                    "reason": self.to_string(),
+                }))
+            }
+            _ => {
+                HttpResponse::InternalServerError().json(json!({
+                    "status": "internal server error",
+                    "reason": self.to_string(),
+                }))
+            }
+        }
+    }
+}
+
+pub fn totp_service_config(config: &mut ServiceConfig) {
+    config
+        .route("", get().to(show))
+        .route("", post().to(enrol))
+        .route("", delete().to(disable))
+        .route("/confirm", post().to(confirm))
+        .route("/recovery-codes", post().to(regenerate_recovery_codes));
+}
+
+#[derive(Deserialize)]
+pub struct CodeRequest {
+    pub code: String,
+}
+
+async fn show(context: Ctx, identity: CurrentIdentity) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let enabled = second_factor_required(&context, &identity.id).await?;
+    let response = HttpResponse::Ok().json(json!({
+        "enabled": enabled,
+    }));
+    Ok(response)
+}
+
+async fn enrol(context: Ctx, identity: CurrentIdentity) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let enrolment = TotpEnrolment::new(&context, &identity);
+    let pending = enrolment.execute().await?;
+    let response = HttpResponse::Ok().json(pending);
+    Ok(response)
+}
+
+async fn confirm(context: Ctx, identity: CurrentIdentity, request: Json<CodeRequest>) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let confirmation = TotpConfirmation::new(&context, &identity.id, &request.code);
+    let recovery_codes = confirmation.execute().await?;
+    let response = HttpResponse::Ok().json(recovery_codes);
+    Ok(response)
+}
+
+async fn regenerate_recovery_codes(context: Ctx, session: Session, identity: CurrentIdentity, request: Json<CodeRequest>) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let regeneration = RecoveryCodeRegeneration::new(&context, &identity.id, &request.code);
+    let recovery_codes = regeneration.execute().await
+        .map_err(|error| count_failed_attempt(&session, MANAGE_ATTEMPTS, error))?;
+    let _ = session.remove(MANAGE_ATTEMPTS);
+    let response = HttpResponse::Ok().json(recovery_codes);
+    Ok(response)
+}
+
+async fn disable(context: Ctx, session: Session, identity: CurrentIdentity, request: Json<CodeRequest>) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let deactivation = TotpDeactivation::new(&context, &identity.id, &request.code);
+    deactivation.execute().await
+        .map_err(|error| count_failed_attempt(&session, MANAGE_ATTEMPTS, error))?;
+    let _ = session.remove(MANAGE_ATTEMPTS);
+    let response = HttpResponse::Ok().json(json!({
+        "enabled": false,
+    }));
+    Ok(response)
+}
+
+/// Counts an invalid code against the session under `key`. Once
+/// `MAX_ATTEMPTS` is reached the session is purged.
+pub(super) fn count_failed_attempt(session: &Session, key: &str, error: TotpError) -> TotpError {
+    if let TotpError::InvalidCode = error {
+        let attempts = session.get::<u32>(key).unwrap_or_default().unwrap_or(0) + 1;
+        if attempts >= MAX_ATTEMPTS {
+            session.purge();
+            return TotpError::TooManyAttempts
+        }
+        if session.insert(key, attempts).is_err() {
+            return TotpError::StateSavingFailed
+        }
+    }
+    error
+}
@@ -0,0 +1,189 @@
+use actix_session::Session;
+use actix_web::{HttpResponse, ResponseError};
+use actix_web::web::{delete, get, post, Data, Json, Path, ServiceConfig};
+use serde_derive::Deserialize;
+use serde_json::json;
+
+use crate::app::context::Context;
+use crate::app::extractors::{CurrentIdentity, record_sign_in};
+use crate::app::models::DomainError;
+use crate::app::models::totp::second_factor_required;
+use crate::app::models::validation::{TextRules, Validate, ValidationErrors, Validator};
+use crate::app::models::webauthn::{
+    AuthenticationOptions, DEFAULT_NAME, NAME_MAX_LENGTH, PasskeyAssertion, PasskeyAttestation, PasskeyAuthentication, PasskeyListing,
+    PasskeyRegistration, PasskeyRemoval, RegistrationOptions, WebAuthnError, generate_challenge,
+};
+
+use super::auth::PENDING_ID;
+
+type Ctx = Data<Context>;
+type Result<T, E = actix_web::Error> = std::result::Result<T, E>;
+
+const REGISTRATION_CHALLENGE: &str = "webauthn-registration-challenge";
+const AUTHENTICATION_CHALLENGE: &str = "webauthn-authentication-challenge";
+
+impl ResponseError for WebAuthnError {
+    fn error_response(&self) -> HttpResponse {
+        match *self {
+            WebAuthnError::ChallengeMissing
+            | WebAuthnError::ChallengeNotMatch
+            | WebAuthnError::InvalidClientData
+            | WebAuthnError::CeremonyNotMatch
+            | WebAuthnError::OriginNotAllowed
+            | WebAuthnError::InvalidAttestation
+            | WebAuthnError::InvalidAuthenticatorData
+            | WebAuthnError::RpIdNotMatch
+            | WebAuthnError::UserNotPresent
+            | WebAuthnError::UnsupportedAlgorithm => {
+                HttpResponse::BadRequest().json(json!({
+                    "status": "Bad Request",
+                    "reason": self.to_string(),
+                }))
+            }
+            WebAuthnError::CredentialNotFound
+            | WebAuthnError::InvalidSignature
+            | WebAuthnError::UserHandleNotMatch
+            | WebAuthnError::CounterNotIncreased => {
+                HttpResponse::Unauthorized().json(json!({
+                    "status": "Unauthorized",
+                    "reason": self.to_string(),
+                }))
+            }
+            WebAuthnError::CredentialAlreadyRegistered => {
+                HttpResponse::Conflict().json(json!({
+                    "status": "Conflict",
+                    "reason": self.to_string(),
+                }))
+            }
+            WebAuthnError::IdentityDeactivated => {
+                HttpResponse::Forbidden().json(json!({
+                    "status": "Forbidden",
+                    "reason": self.to_string(),
+                }))
+            }
+            _ => {
+                HttpResponse::InternalServerError().json(json!({
+                    "status": "internal server error",
+                    "reason": self.to_string(),
+                }))
+            }
+        }
+    }
+}
+
+pub fn webauthn_service_config(config: &mut ServiceConfig) {
+    config
+        .route("/register/start", post().to(start_registration))
+        .route("/register/finish", post().to(finish_registration))
+        .route("/login/start", post().to(start_authentication))
+        .route("/login/finish", post().to(finish_authentication))
+        .route("/credentials", get().to(list))
+        .route("/credentials/{id}", delete().to(remove));
+}
+
+async fn start_registration(context: Ctx, session: Session, identity: CurrentIdentity) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let challenge = generate_challenge();
+    let options = RegistrationOptions::new(&context, &identity, &challenge).execute().await?;
+    session.insert(REGISTRATION_CHALLENGE, &challenge)
+        .or(Err(WebAuthnError::StateSavingFailed))?;
+
+    let response = HttpResponse::Ok().json(json!({
+        "publicKey": options,
+    }));
+    Ok(response)
+}
+
+#[derive(Deserialize)]
+struct FinishRegistrationRequest {
+    #[serde(flatten)]
+    credential: PasskeyAttestation,
+    name: Option<String>,
+}
+
+impl Validate for FinishRegistrationRequest {
+    type Output = String;
+
+    fn validate(&self) -> std::result::Result<Self::Output, ValidationErrors> {
+        let mut validator = Validator::new();
+        let name_rules = TextRules::new().max_length(NAME_MAX_LENGTH);
+        let name = validator.text("name", self.name.as_deref(), &name_rules);
+        validator.finish(Some(name.unwrap_or_else(|| DEFAULT_NAME.to_owned())))
+    }
+}
+
+async fn finish_registration(context: Ctx, session: Session, identity: CurrentIdentity, request: Json<FinishRegistrationRequest>) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let name = request.validate().map_err(DomainError::from)?;
+    let challenge = take_challenge(&session, REGISTRATION_CHALLENGE)?;
+    let registration = PasskeyRegistration::new(&context, &identity.id, challenge, request.into_inner().credential, &name);
+    let passkey = registration.execute().await?;
+    let response = HttpResponse::Created().json(passkey);
+    Ok(response)
+}
+
+async fn start_authentication(context: Ctx, session: Session) -> Result<HttpResponse> {
+    let challenge = generate_challenge();
+    session.insert(AUTHENTICATION_CHALLENGE, &challenge)
+        .or(Err(WebAuthnError::StateSavingFailed))?;
+
+    let response = HttpResponse::Ok().json(json!({
+        "publicKey": AuthenticationOptions::new(&context, &challenge),
+    }));
+    Ok(response)
+}
+
+async fn finish_authentication(context: Ctx, session: Session, assertion: Json<PasskeyAssertion>) -> Result<HttpResponse> {
+    let challenge = take_challenge(&session, AUTHENTICATION_CHALLENGE)?;
+    let authentication = PasskeyAuthentication::new(&context, challenge, assertion.into_inner());
+    let identity = authentication.execute().await?;
+
+    // A passkey stands in for the password, not for TOTP: identities that
+    // enabled it still have to pass it at /auth/totp.
+    let second_factor = second_factor_required(&context, &identity.id).await?;
+    session.clear();
+    session.renew();
+    if second_factor {
+        session.insert(PENDING_ID, &identity.id)
+            .or(Err(WebAuthnError::StateSavingFailed))?;
+    } else {
+        session.insert("id", &identity.id)
+            .or(Err(WebAuthnError::StateSavingFailed))?;
+        record_sign_in(&session)
+            .or(Err(WebAuthnError::StateSavingFailed))?;
+    }
+
+    let response = HttpResponse::Ok().json(json!({
+        "identifier": identity.id,
+        "name": identity.name.or(identity.login),
+        "second_factor_required": second_factor,
+    }));
+    Ok(response)
+}
+
+async fn list(context: Ctx, identity: CurrentIdentity) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let listing = PasskeyListing::new(&context, &identity.id);
+    let passkeys = listing.execute().await?;
+    let response = HttpResponse::Ok().json(json!({
+        "passkeys": passkeys,
+    }));
+    Ok(response)
+}
+
+async fn remove(context: Ctx, identity: CurrentIdentity, path: Path<String>) -> Result<HttpResponse> {
+    identity.require_session()?;
+    let id = path.into_inner();
+    let removal = PasskeyRemoval::new(&context, &identity.id, &id);
+    let passkey = removal.execute().await?;
+    let response = HttpResponse::Ok().json(passkey);
+    Ok(response)
+}
+
+/// Challenges are single use: whatever the outcome, the ceremony has to be
+/// started again.
+fn take_challenge(session: &Session, key: &str) -> std::result::Result<Option<String>, WebAuthnError> {
+    let challenge = session.get(key).or(Err(WebAuthnError::StateLoadingFailed))?;
+    let _ = session.remove(key);
+    Ok(challenge)
+}
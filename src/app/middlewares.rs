@@ -1,3 +1,11 @@
+mod csrf_protection;
 mod login_required;
+mod role_required;
+mod scope_required;
+mod session_key_rotation;
 
+pub use csrf_protection::{CSRF_HEADER, CsrfProtection, csrf_token};
 pub use login_required::LoginRequired;
+pub use role_required::RoleRequired;
+pub use scope_required::ScopeRequired;
+pub use session_key_rotation::SessionKeyRotation;
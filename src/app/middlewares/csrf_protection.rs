@@ -0,0 +1,141 @@
+use std::rc::Rc;
+
+use actix_session::{Session, SessionExt};
+use actix_web::{Error, HttpResponse};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::Method;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use futures_util::future::{ok, FutureExt as _, LocalBoxFuture, Ready};
+use rand::{RngExt, rngs::StdRng};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::app::extractors::CurrentIdentity;
+
+pub const CSRF_HEADER: &str = "X-CSRF-Token";
+const CSRF_SESSION_KEY: &str = "csrf-token";
+
+/// Returns the session's CSRF token, creating one on first use.
+pub fn csrf_token(session: &Session) -> Result<String, Error> {
+    if let Some(token) = session.get::<String>(CSRF_SESSION_KEY)? {
+        return Ok(token)
+    }
+    let mut rng: StdRng = rand::make_rng();
+    let mut rs: [u8; 32] = [0; 32];
+    rng.fill(&mut rs);
+    let token = URL_SAFE_NO_PAD.encode(rs);
+    session.insert(CSRF_SESSION_KEY, &token)?;
+    Ok(token)
+}
+
+/// Synchronizer-token CSRF check. Unsafe methods on a session that carries
+/// a sign-in must echo the session's token in `X-CSRF-Token`. Requests with
+/// a bearer token are exempt, since browsers never attach one on their own.
+#[derive(Default)]
+pub struct CsrfProtection {
+}
+
+impl CsrfProtection {
+    pub fn new() -> Self {
+        Self {
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for CsrfProtection
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>> + 'static,
+    S::Future: 'static,
+    S::Error: 'static,
+    B: MessageBody + 'static,
+    B::Error: Into<Error>,
+{
+    type Response = ServiceResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = CsrfProtectionMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(CsrfProtectionMiddleware {
+            service: Rc::new(service),
+        })
+    }
+}
+
+pub struct CsrfProtectionMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for CsrfProtectionMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>> + 'static,
+    S::Future: 'static,
+    S::Error: 'static,
+    B: MessageBody + 'static,
+    B::Error: Into<Error>,
+{
+    type Response = ServiceResponse;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        async move {
+            let validator = CsrfValidator::new(&req);
+            if let Err(res) = validator.execute() {
+                let response = req.into_response(res);
+                return Ok(response)
+            }
+
+            let res = service.call(req).await?;
+            Ok(res.map_body(|_, body| BoxBody::new(body)))
+        }
+        .boxed_local()
+    }
+}
+
+struct CsrfValidator<'a> {
+    request: &'a ServiceRequest,
+}
+
+impl<'a> CsrfValidator<'a> {
+    fn new(request: &'a ServiceRequest) -> Self {
+        Self {
+            request,
+        }
+    }
+
+    fn execute(&self) -> std::result::Result<(), HttpResponse> {
+        let method = self.request.method();
+        if method == Method::GET || method == Method::HEAD || method == Method::OPTIONS {
+            return Ok(())
+        }
+        if CurrentIdentity::bearer_token(self.request.request()).is_some() {
+            return Ok(())
+        }
+
+        let session = self.request.get_session();
+        let signed_in = session.contains_key("id") || session.contains_key("pending-id");
+        if !signed_in {
+            return Ok(())
+        }
+
+        let expected = session.get::<String>(CSRF_SESSION_KEY).ok().flatten();
+        let presented = self.request.headers().get(CSRF_HEADER).and_then(|value| value.to_str().ok());
+        match (expected, presented) {
+            (Some(expected), Some(presented)) if Self::matches(&expected, presented) => Ok(()),
+            _ => Err(HttpResponse::Forbidden().json(json!({
+                "error": "invalid csrf token",
+            }))),
+        }
+    }
+
+    /// Compares digests so the comparison time says nothing about the token.
+    fn matches(expected: &str, presented: &str) -> bool {
+        Sha256::digest(expected.as_bytes()) == Sha256::digest(presented.as_bytes())
+    }
+}
@@ -1,10 +1,15 @@
-use actix_session::{SessionExt};
-use actix_web::{Error, HttpResponse};
+use std::rc::Rc;
+
+use actix_web::{Error, FromRequest, ResponseError};
 use actix_web::body::{BoxBody, MessageBody};
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
 use futures_util::future::{ok, FutureExt as _, LocalBoxFuture, Ready};
-use serde_json::json;
 
+use crate::app::extractors::{CurrentIdentity, CurrentIdentityError};
+
+/// Requires a signed-in identity. Loading it also holds session sign-ins to
+/// their timeouts, so handlers behind this need not extract one themselves.
+#[derive(Default)]
 pub struct LoginRequired {
 }
 
@@ -17,7 +22,7 @@ impl LoginRequired {
 
 impl<S, B> Transform<S, ServiceRequest> for LoginRequired
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>> + 'static,
     S::Future: 'static,
     S::Error: 'static,
     B: MessageBody + 'static,
@@ -31,18 +36,18 @@ where
 
     fn new_transform(&self, service: S) -> Self::Future {
         ok(LoginRequiredMiddleware {
-            service: service,
+            service: Rc::new(service),
         })
     }
 }
 
 pub struct LoginRequiredMiddleware<S> {
-    service: S,
+    service: Rc<S>,
 }
 
 impl<S, B> Service<ServiceRequest> for LoginRequiredMiddleware<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>> + 'static,
     S::Future: 'static,
     S::Error: 'static,
     B: MessageBody + 'static,
@@ -55,16 +60,16 @@ where
     actix_service::forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        let validator = LoginValidator::new(&req);
-        let login_status = validator.execute();
-        if let Err(res) = login_status {
-            let response = req.into_response(res);
-            return Box::pin(ok(response))
-        }
-
-        let fut = self.service.call(req);
+        let service = Rc::clone(&self.service);
         async move {
-            let res = fut.await?;
+            let validator = LoginValidator::new(&req);
+            let login_status = validator.execute().await;
+            if let Err(error) = login_status {
+                let response = req.into_response(error.error_response());
+                return Ok(response)
+            }
+
+            let res = service.call(req).await?;
             Ok(res.map_body(|_, body| BoxBody::new(body)))
         }
         .boxed_local()
@@ -78,27 +83,12 @@ struct LoginValidator<'a> {
 impl<'a> LoginValidator<'a> {
     fn new(request: &'a ServiceRequest) -> Self {
         Self {
-            request: request,
+            request,
         }
     }
 
-    fn execute(&self) -> std::result::Result<(), HttpResponse> {
-        let session = self.request.get_session();
-        let value = session.get::<String>("id");
-        match value {
-            Ok(Some(_id)) => Ok(()),
-            Ok(None) => {
-                let response = HttpResponse::Unauthorized().json(json!({
-                    "error": "login required",
-                }));
-                Err(response)
-            },
-            Err(_error) => {
-                let response = HttpResponse::InternalServerError().json(json!({
-                    "error": "internal server error",
-                }));
-                Err(response)
-            }
-        }
+    async fn execute(&self) -> std::result::Result<(), CurrentIdentityError> {
+        CurrentIdentity::extract(self.request.request()).await?;
+        Ok(())
     }
 }
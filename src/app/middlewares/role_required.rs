@@ -0,0 +1,107 @@
+use std::rc::Rc;
+
+use actix_web::{Error, FromRequest, HttpResponse, ResponseError};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use futures_util::future::{ok, FutureExt as _, LocalBoxFuture, Ready};
+
+use crate::app::extractors::{CurrentIdentity, CurrentIdentityError};
+use crate::app::models::identity::Role;
+
+/// Rejects requests from identities without `role`. Only session logins
+/// are accepted, so access tokens never reach role-guarded routes.
+pub struct RoleRequired {
+    role: Role,
+}
+
+impl RoleRequired {
+    pub fn new(role: Role) -> Self {
+        Self {
+            role,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RoleRequired
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>> + 'static,
+    S::Future: 'static,
+    S::Error: 'static,
+    B: MessageBody + 'static,
+    B::Error: Into<Error>,
+{
+    type Response = ServiceResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = RoleRequiredMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(RoleRequiredMiddleware {
+            service: Rc::new(service),
+            role: self.role,
+        })
+    }
+}
+
+pub struct RoleRequiredMiddleware<S> {
+    service: Rc<S>,
+    role: Role,
+}
+
+impl<S, B> Service<ServiceRequest> for RoleRequiredMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>> + 'static,
+    S::Future: 'static,
+    S::Error: 'static,
+    B: MessageBody + 'static,
+    B::Error: Into<Error>,
+{
+    type Response = ServiceResponse;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let role = self.role;
+        async move {
+            let validator = RoleValidator::new(&req, role);
+            let role_status = validator.execute().await;
+            if let Err(res) = role_status {
+                let response = req.into_response(res);
+                return Ok(response)
+            }
+
+            let res = service.call(req).await?;
+            Ok(res.map_body(|_, body| BoxBody::new(body)))
+        }
+        .boxed_local()
+    }
+}
+
+struct RoleValidator<'a> {
+    request: &'a ServiceRequest,
+    role: Role,
+}
+
+impl<'a> RoleValidator<'a> {
+    fn new(request: &'a ServiceRequest, role: Role) -> Self {
+        Self {
+            request,
+            role,
+        }
+    }
+
+    async fn execute(&self) -> std::result::Result<(), HttpResponse> {
+        let identity = CurrentIdentity::extract(self.request.request()).await
+            .map_err(|error| error.error_response())?;
+        identity.require_session()
+            .map_err(|error| error.error_response())?;
+        if identity.role != self.role {
+            return Err(CurrentIdentityError::RoleMissing(self.role).error_response())
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use std::rc::Rc;
+
+use actix_web::{Error, FromRequest, HttpResponse, ResponseError};
+use actix_web::body::{BoxBody, MessageBody};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use futures_util::future::{ok, FutureExt as _, LocalBoxFuture, Ready};
+
+use crate::app::extractors::{CurrentIdentity, CurrentIdentityError};
+use crate::app::models::scope::Scope;
+
+/// Rejects requests whose credential was not granted `scope`. Session
+/// logins hold every scope; access tokens only those they were created with.
+pub struct ScopeRequired {
+    scope: Scope,
+}
+
+impl ScopeRequired {
+    pub fn new(scope: Scope) -> Self {
+        Self {
+            scope,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ScopeRequired
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>> + 'static,
+    S::Future: 'static,
+    S::Error: 'static,
+    B: MessageBody + 'static,
+    B::Error: Into<Error>,
+{
+    type Response = ServiceResponse;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = ScopeRequiredMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(ScopeRequiredMiddleware {
+            service: Rc::new(service),
+            scope: self.scope,
+        })
+    }
+}
+
+pub struct ScopeRequiredMiddleware<S> {
+    service: Rc<S>,
+    scope: Scope,
+}
+
+impl<S, B> Service<ServiceRequest> for ScopeRequiredMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>> + 'static,
+    S::Future: 'static,
+    S::Error: 'static,
+    B: MessageBody + 'static,
+    B::Error: Into<Error>,
+{
+    type Response = ServiceResponse;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let scope = self.scope;
+        async move {
+            let validator = ScopeValidator::new(&req, scope);
+            let scope_status = validator.execute().await;
+            if let Err(res) = scope_status {
+                let response = req.into_response(res);
+                return Ok(response)
+            }
+
+            let res = service.call(req).await?;
+            Ok(res.map_body(|_, body| BoxBody::new(body)))
+        }
+        .boxed_local()
+    }
+}
+
+struct ScopeValidator<'a> {
+    request: &'a ServiceRequest,
+    scope: Scope,
+}
+
+impl<'a> ScopeValidator<'a> {
+    fn new(request: &'a ServiceRequest, scope: Scope) -> Self {
+        Self {
+            request,
+            scope,
+        }
+    }
+
+    async fn execute(&self) -> std::result::Result<(), HttpResponse> {
+        let identity = CurrentIdentity::extract(self.request.request()).await
+            .map_err(|error| error.error_response())?;
+        if !identity.has_scope(self.scope) {
+            return Err(CurrentIdentityError::ScopeMissing(self.scope).error_response())
+        }
+        Ok(())
+    }
+}
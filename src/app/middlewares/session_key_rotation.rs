@@ -0,0 +1,227 @@
+use std::rc::Rc;
+
+use actix_web::body::MessageBody;
+use actix_web::cookie::{time, Cookie, CookieJar, Key};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{self, HeaderValue};
+use futures_util::future::{ok, FutureExt as _, LocalBoxFuture, Ready};
+
+use crate::app::config::SessionConfig;
+
+/// Accepts session cookies sealed with a retired key. It has to wrap the
+/// session middleware: a cookie sealed with an older key is re-sealed with
+/// the primary key before the session is loaded, and handed back to the
+/// client unless the session middleware issues a fresh one itself.
+pub struct SessionKeyRotation {
+    keys: Rc<Vec<Key>>,
+    config: Rc<SessionConfig>,
+}
+
+impl SessionKeyRotation {
+    /// `keys` starts with the primary key, the one the session middleware
+    /// is configured with.
+    pub fn new(keys: Vec<Key>, config: &SessionConfig) -> Self {
+        Self {
+            keys: Rc::new(keys),
+            config: Rc::new(config.clone()),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for SessionKeyRotation
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>> + 'static,
+    S::Future: 'static,
+    S::Error: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = S::Error;
+    type InitError = ();
+    type Transform = SessionKeyRotationMiddleware<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(SessionKeyRotationMiddleware {
+            service: Rc::new(service),
+            keys: Rc::clone(&self.keys),
+            config: Rc::clone(&self.config),
+        })
+    }
+}
+
+pub struct SessionKeyRotationMiddleware<S> {
+    service: Rc<S>,
+    keys: Rc<Vec<Key>>,
+    config: Rc<SessionConfig>,
+}
+
+impl<S, B> Service<ServiceRequest> for SessionKeyRotationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>> + 'static,
+    S::Future: 'static,
+    S::Error: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = S::Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    actix_service::forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let resealer = CookieResealer::new(&self.keys, &self.config);
+        let resealed = resealer.execute(&mut req);
+        async move {
+            let mut res = service.call(req).await?;
+            let reissue = resealed
+                .filter(|cookie| !res.response().cookies().any(|issued| issued.name() == cookie.name()));
+            if let Some(cookie) = reissue {
+                if let Err(e) = res.response_mut().add_cookie(&cookie) {
+                    log::warn!("Failed to re-issue session cookie: {}", e);
+                }
+            }
+            Ok(res)
+        }
+        .boxed_local()
+    }
+}
+
+struct CookieResealer<'a> {
+    keys: &'a [Key],
+    config: &'a SessionConfig,
+}
+
+impl<'a> CookieResealer<'a> {
+    fn new(keys: &'a [Key], config: &'a SessionConfig) -> Self {
+        Self {
+            keys,
+            config,
+        }
+    }
+
+    /// Rewrites the request's session cookie if an older key sealed it, and
+    /// returns the cookie to send back.
+    fn execute(&self, req: &mut ServiceRequest) -> Option<Cookie<'static>> {
+        let (primary, retired) = self.keys.split_first()?;
+        let name = self.config.cookie_name.as_str();
+        let pairs = Self::cookie_pairs(req);
+        let original = pairs.iter()
+            .filter_map(|pair| Cookie::parse_encoded(pair.as_str()).ok())
+            .find(|cookie| cookie.name() == name)?;
+
+        let mut jar = CookieJar::new();
+        jar.add_original(original.into_owned());
+        if jar.private(primary).get(name).is_some() {
+            return None
+        }
+        let opened = retired.iter().find_map(|key| jar.private(key).get(name))?;
+
+        let mut sealed_jar = CookieJar::new();
+        sealed_jar.private_mut(primary).add(opened);
+        let sealed = sealed_jar.get(name)?.clone();
+
+        // Cookies are parsed lazily, so the session middleware reads the
+        // rewritten header.
+        let rewritten = pairs.iter()
+            .map(|pair| match Cookie::parse_encoded(pair.as_str()) {
+                Ok(cookie) if cookie.name() == name => sealed.encoded().to_string(),
+                _ => pair.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        let header_value = HeaderValue::from_str(&rewritten).ok()?;
+        req.headers_mut().insert(header::COOKIE, header_value);
+        Some(self.session_cookie(sealed.value().to_owned()))
+    }
+
+    fn cookie_pairs(req: &ServiceRequest) -> Vec<String> {
+        req.headers().get_all(header::COOKIE)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(';'))
+            .map(|pair| pair.trim().to_owned())
+            .filter(|pair| !pair.is_empty())
+            .collect()
+    }
+
+    /// Mirrors the attributes the session middleware gives its cookie.
+    fn session_cookie(&self, value: String) -> Cookie<'static> {
+        let mut cookie = Cookie::new(self.config.cookie_name.clone(), value);
+        cookie.set_path(self.config.cookie_path.clone());
+        cookie.set_secure(self.config.cookie_secure);
+        cookie.set_http_only(true);
+        cookie.set_same_site(self.config.same_site());
+        cookie.set_max_age(time::Duration::seconds(self.config.absolute_timeout_seconds));
+        if let Some(domain) = &self.config.cookie_domain {
+            cookie.set_domain(domain.clone());
+        }
+        cookie
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    fn sealed(key: &Key, name: &str, value: &str) -> Cookie<'static> {
+        let mut jar = CookieJar::new();
+        jar.private_mut(key).add(Cookie::new(name.to_owned(), value.to_owned()));
+        jar.get(name).unwrap().clone()
+    }
+
+    fn open(key: &Key, req: &ServiceRequest, name: &str) -> Option<String> {
+        let mut jar = CookieJar::new();
+        jar.add_original(req.cookie(name)?);
+        jar.private(key).get(name).map(|cookie| cookie.value().to_owned())
+    }
+
+    #[test]
+    fn reseals_cookies_from_retired_keys() {
+        let keys = vec![Key::generate(), Key::generate()];
+        let config = SessionConfig::default();
+        let mut req = TestRequest::default()
+            .cookie(sealed(&keys[1], "id", "session-key"))
+            .cookie(Cookie::new("theme", "dark"))
+            .to_srv_request();
+
+        let reissued = CookieResealer::new(&keys, &config).execute(&mut req).unwrap();
+        assert_eq!(reissued.name(), "id");
+        assert_eq!(reissued.http_only(), Some(true));
+        assert_eq!(open(&keys[0], &req, "id").as_deref(), Some("session-key"));
+        assert_eq!(req.cookie("theme").unwrap().value(), "dark");
+    }
+
+    #[test]
+    fn leaves_current_cookies_alone() {
+        let keys = vec![Key::generate(), Key::generate()];
+        let config = SessionConfig::default();
+        let cookie = sealed(&keys[0], "id", "session-key");
+        let mut req = TestRequest::default().cookie(cookie.clone()).to_srv_request();
+
+        assert!(CookieResealer::new(&keys, &config).execute(&mut req).is_none());
+        assert_eq!(req.cookie("id").unwrap().value(), cookie.value());
+    }
+
+    #[test]
+    fn ignores_cookies_from_unknown_keys() {
+        let keys = vec![Key::generate(), Key::generate()];
+        let config = SessionConfig::default();
+        let cookie = sealed(&Key::generate(), "id", "session-key");
+        let mut req = TestRequest::default().cookie(cookie.clone()).to_srv_request();
+
+        assert!(CookieResealer::new(&keys, &config).execute(&mut req).is_none());
+        assert_eq!(req.cookie("id").unwrap().value(), cookie.value());
+    }
+
+    #[test]
+    fn ignores_requests_without_a_session_cookie() {
+        let keys = vec![Key::generate(), Key::generate()];
+        let config = SessionConfig::default();
+        let mut req = TestRequest::default().cookie(sealed(&keys[1], "other", "value")).to_srv_request();
+
+        assert!(CookieResealer::new(&keys, &config).execute(&mut req).is_none());
+    }
+}
@@ -1,18 +1,36 @@
 use thiserror::Error;
 
+pub mod access_token;
 pub mod auth;
+pub mod credential;
 pub mod identity;
 pub mod servant;
+pub mod scope;
+pub mod session;
+pub mod token_pair;
+pub mod totp;
+pub mod validation;
+pub mod webauthn;
 
 pub use identity::Identity;
 
 use super::db::DatabaseError;
+use self::validation::ValidationErrors;
 
 #[derive(Error, Debug)]
 pub enum DomainError {
     #[error("Requested record is not found")]
     RecordNotFound,
 
+    #[error("The last linked credential cannot be unlinked")]
+    LastCredential,
+
+    #[error("Validation failed")]
+    ValidationFailed {
+        #[from]
+        source: ValidationErrors,
+    },
+
     #[error("Database error: {source}")]
     DatabaseError {
         #[source]
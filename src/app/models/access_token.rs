@@ -0,0 +1,35 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::{RngExt, rngs::StdRng};
+use sha2::{Digest, Sha256};
+
+pub use crate::app::db::access_token_repository::AccessToken;
+
+pub const NAME_MAX_LENGTH: usize = 100;
+pub const MAX_TTL_DAYS: i64 = 365;
+
+/// Prefix of every personal access token, telling them apart from other
+/// bearer credentials and making leaked tokens easy to scan for.
+pub const TOKEN_PREFIX: &str = "axp_";
+
+fn generate_secret() -> String {
+    let mut rng: StdRng = rand::make_rng();
+    let mut rs: [u8; 32] = [0; 32];
+    rng.fill(&mut rs);
+    format!("{}{}", TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(rs))
+}
+
+/// Tokens carry 256 random bits, so a plain SHA-256 is enough to keep
+/// stored hashes from being usable as credentials.
+pub fn hash_secret(secret: &str) -> String {
+    let digest = Sha256::digest(secret.as_bytes());
+    URL_SAFE_NO_PAD.encode(digest)
+}
+
+mod issuance;
+pub use issuance::{AccessTokenIssuance, IssuedAccessToken};
+
+mod listing;
+pub use listing::AccessTokenListing;
+
+mod revocation;
+pub use revocation::AccessTokenRevocation;
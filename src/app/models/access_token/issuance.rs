@@ -0,0 +1,58 @@
+use serde_derive::Serialize;
+
+use crate::app::context::Context;
+use crate::app::db::access_token_repository::{AccessTokenDataset, AccessTokenRepository};
+use crate::app::models::DomainError;
+use crate::app::models::scope::Scope;
+use super::{AccessToken, generate_secret, hash_secret};
+
+/// A freshly created token. The secret is only ever shown here; afterwards
+/// just its hash is kept.
+#[derive(Serialize)]
+pub struct IssuedAccessToken {
+    #[serde(flatten)]
+    pub token: AccessToken,
+    pub secret: String,
+}
+
+pub struct AccessTokenIssuance<'a> {
+    context: &'a Context,
+    identity_id: String,
+    name: String,
+    scopes: Vec<Scope>,
+    ttl_days: Option<i64>,
+}
+
+impl<'a> AccessTokenIssuance<'a> {
+    pub fn new(context: &'a Context, identity_id: &str, name: &str, scopes: Vec<Scope>, ttl_days: Option<i64>) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+            name: name.to_owned(),
+            scopes,
+            ttl_days,
+        }
+    }
+
+    pub async fn execute(self) -> Result<IssuedAccessToken, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = AccessTokenRepository::new(&connection);
+        let secret = generate_secret();
+        let mut scopes: Vec<String> = self.scopes.iter().map(|scope| scope.as_str().to_owned()).collect();
+        scopes.sort();
+        scopes.dedup();
+        let dataset = AccessTokenDataset {
+            name: self.name,
+            token_hash: hash_secret(&secret),
+            scopes,
+            ttl_days: self.ttl_days,
+        };
+        let token = repository.create(&self.identity_id, &dataset).await?;
+
+        let issued = IssuedAccessToken {
+            token,
+            secret,
+        };
+        Ok(issued)
+    }
+}
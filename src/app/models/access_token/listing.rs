@@ -0,0 +1,25 @@
+use crate::app::context::Context;
+use crate::app::db::access_token_repository::AccessTokenRepository;
+use crate::app::models::DomainError;
+use super::AccessToken;
+
+pub struct AccessTokenListing<'a> {
+    context: &'a Context,
+    identity_id: String,
+}
+
+impl<'a> AccessTokenListing<'a> {
+    pub fn new(context: &'a Context, identity_id: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<Vec<AccessToken>, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = AccessTokenRepository::new(&connection);
+        let tokens = repository.list(&self.identity_id).await?;
+        Ok(tokens)
+    }
+}
@@ -0,0 +1,27 @@
+use crate::app::context::Context;
+use crate::app::db::access_token_repository::AccessTokenRepository;
+use crate::app::models::DomainError;
+use super::AccessToken;
+
+pub struct AccessTokenRevocation<'a> {
+    context: &'a Context,
+    identity_id: String,
+    id: String,
+}
+
+impl<'a> AccessTokenRevocation<'a> {
+    pub fn new(context: &'a Context, identity_id: &str, id: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+            id: id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<AccessToken, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = AccessTokenRepository::new(&connection);
+        let token = repository.delete(&self.identity_id, &self.id).await?;
+        Ok(token)
+    }
+}
@@ -2,31 +2,95 @@ use std::result::Result;
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use rand::{RngExt, rngs::StdRng};
+use reqwest::Url;
 use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 
-use crate::app::config::ApplicationConfig;
 use crate::app::context::Context;
-use crate::app::db::identity_repository::IdentityRepository;
+use crate::app::db::DatabaseError;
+use crate::app::db::credential_repository::CredentialRepository;
+use crate::app::db::identity_repository::{IdentityRepository, ProfileDataset};
 use crate::app::models::Identity;
 
+mod discovery;
+mod github;
+mod gitlab;
+mod oidc;
+mod provider;
+mod return_to;
+
+pub use discovery::DiscoveryCache;
+pub use provider::{IdentityProvider, find_provider};
+pub use return_to::resolve_return_to;
+
+pub const CODE_CHALLENGE_METHOD: &str = "S256";
+
 pub struct AuthorizationRequest {
     pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub code_challenge: String,
+}
+
+impl Default for AuthorizationRequest {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AuthorizationRequest {
     pub fn new() -> Self {
+        let code_verifier = Self::generate_state();
         Self {
             state: Self::generate_state(),
+            nonce: Self::generate_state(),
+            code_challenge: Self::generate_code_challenge(&code_verifier),
+            code_verifier,
         }
     }
 
+    /// Builds the provider URL the browser is redirected to for sign-in.
+    pub fn authorize_url(&self, provider: &dyn IdentityProvider) -> Result<String, AuthenticationError> {
+        let config = provider.config();
+        let endpoints = provider.endpoints();
+        let mut parameters = vec![
+            ("response_type", "code"),
+            ("client_id", &config.client_id),
+            ("scope", &endpoints.scope),
+            ("state", &self.state),
+            ("nonce", &self.nonce),
+            ("code_challenge", &self.code_challenge),
+            ("code_challenge_method", CODE_CHALLENGE_METHOD),
+        ];
+        if let Some(redirect_uri) = &config.redirect_uri {
+            parameters.push(("redirect_uri", redirect_uri));
+        }
+        let url = Url::parse_with_params(&endpoints.authorize_url, &parameters)
+            .or(Err(AuthenticationError::ProviderMisconfigured))?;
+        Ok(url.into())
+    }
+
     fn generate_state() -> String {
         let mut rng: StdRng = rand::make_rng();
         let mut rs: [u8; 32] = [0; 32];
         rng.fill(&mut rs);
         URL_SAFE_NO_PAD.encode(rs)
     }
+
+    fn generate_code_challenge(code_verifier: &str) -> String {
+        let digest = Sha256::digest(code_verifier.as_bytes());
+        URL_SAFE_NO_PAD.encode(digest)
+    }
+}
+
+/// Values kept in the session between starting a sign-in and its callback.
+pub struct SavedAuthorization {
+    pub state: Option<String>,
+    pub nonce: Option<String>,
+    pub code_verifier: Option<String>,
+    /// Set when a signed-in identity started the flow to link a provider.
+    pub link_identity_id: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -77,75 +141,152 @@ pub enum AuthenticationError {
 
     #[error("Failed to find/register identity")]
     IdentityRegistrationFailed,
+
+    #[error("Identity is deactivated")]
+    IdentityDeactivated,
+
+    #[error("Unknown identity provider")]
+    UnknownProvider,
+
+    #[error("Identity provider is misconfigured")]
+    ProviderMisconfigured,
+
+    #[error("OpenID provider discovery failed")]
+    DiscoveryFailed,
+
+    #[error("Token response has no ID token")]
+    IdTokenMissing,
+
+    #[error("ID token is malformed")]
+    InvalidIdToken,
+
+    #[error("No signing key found for ID token")]
+    SigningKeyNotFound,
+
+    #[error("ID token signature is invalid")]
+    InvalidIdTokenSignature,
+
+    #[error("ID token issuer does not match")]
+    IssuerNotMatch,
+
+    #[error("ID token audience does not match")]
+    AudienceNotMatch,
+
+    #[error("ID token is expired")]
+    IdTokenExpired,
+
+    #[error("ID token nonce does not match saved one")]
+    NonceNotMatch,
+
+    #[error("Redirect target is not allowed")]
+    ReturnToNotAllowed,
+
+    #[error("Credential is already linked to another identity")]
+    CredentialAlreadyLinked,
 }
 
 pub struct Authentication<'a> {
     context: &'a Context,
+    provider: &'a dyn IdentityProvider,
     params: CallbackParams,
-    saved_state: Option<String>,
+    saved: SavedAuthorization,
 }
 
 impl<'a> Authentication<'a> {
-    pub fn new(context: &'a Context, params: CallbackParams, saved_state: Option<String>) -> Self {
+    pub fn new(context: &'a Context, provider: &'a dyn IdentityProvider, params: CallbackParams, saved: SavedAuthorization) -> Self {
         Self {
-            context: context,
-            params: params,
-            saved_state: saved_state,
+            context,
+            provider,
+            params,
+            saved,
         }
     }
 
     pub async fn execute(self) -> Result<AuthenticationResult, AuthenticationError> {
-        let saved_state = self.saved_state.ok_or(AuthenticationError::StateMissing)?;
+        let saved_state = self.saved.state.ok_or(AuthenticationError::StateMissing)?;
         if self.params.state != saved_state {
             return Err(AuthenticationError::StateNotMatch)
         }
+        let code_verifier = self.saved.code_verifier.ok_or(AuthenticationError::StateMissing)?;
 
-        let config = &self.context.config;
-        let token_response = TokenRequest::new(config, self.params.code, self.params.state)
+        let token_response = TokenRequest::new(self.provider, self.params.code, self.params.state, code_verifier)
             .execute()
             .await?;
 
-        let user_response = UserRequest::new(token_response.access_token).execute().await?;
+        let user = self.provider.fetch_user(&token_response, self.saved.nonce.as_deref()).await?;
+        let provider_identifier = self.provider.provider_identifier(&user);
 
         let connection = self.context.db.establish_connection().await
             .or(Err(AuthenticationError::DatabaseConnectionFailed))?;
-        let repository = IdentityRepository::new(&connection);
-        let identity = repository.find_or_create(&user_response.id.to_string()).await.or(Err(AuthenticationError::IdentityRegistrationFailed))?;
+        let identity = match &self.saved.link_identity_id {
+            Some(identity_id) => {
+                let credentials = CredentialRepository::new(&connection);
+                credentials.link(identity_id, &provider_identifier, &user.login).await
+                    .or(Err(AuthenticationError::IdentityRegistrationFailed))?
+                    .ok_or(AuthenticationError::CredentialAlreadyLinked)?;
+                let repository = IdentityRepository::new(&connection);
+                match repository.find_by_id(identity_id).await {
+                    Ok(identity) => identity,
+                    Err(DatabaseError::NotFound) => return Err(AuthenticationError::StateNotMatch),
+                    Err(_) => return Err(AuthenticationError::IdentityRegistrationFailed),
+                }
+            }
+            None => {
+                let repository = IdentityRepository::new(&connection);
+                let profile = ProfileDataset {
+                    login: user.login.clone(),
+                    name: user.name.clone(),
+                    avatar_url: user.avatar_url,
+                };
+                repository.register(&provider_identifier, &profile).await.or(Err(AuthenticationError::IdentityRegistrationFailed))?
+            }
+        };
+        if !identity.alive {
+            return Err(AuthenticationError::IdentityDeactivated)
+        }
 
         let result = AuthenticationResult {
-            identity: identity,
-            identifier: user_response.id.to_string(),
-            username: user_response.login,
-            name: user_response.name,
+            identity,
+            identifier: provider_identifier,
+            name: user.name.unwrap_or_else(|| user.login.clone()),
+            username: user.login,
         };
         Ok(result)
     }
 }
 
 struct TokenRequest<'a> {
-    config: &'a ApplicationConfig,
+    provider: &'a dyn IdentityProvider,
     code: String,
     state: String,
+    code_verifier: String,
 }
 
 impl<'a> TokenRequest<'a> {
-    fn new(config: &'a ApplicationConfig, code: String, state: String) -> Self {
+    fn new(provider: &'a dyn IdentityProvider, code: String, state: String, code_verifier: String) -> Self {
         Self {
-            config: config,
-            code: code,
-            state: state,
+            provider,
+            code,
+            state,
+            code_verifier,
         }
     }
 
     async fn execute(&self) -> Result<TokenResponse, AuthenticationError> {
         let client = reqwest::Client::new();
-        let parameters = [
-            ("client_id", &self.config.auth.client_id),
-            ("client_secret", &self.config.auth.client_secret),
+        let config = self.provider.config();
+        let mut parameters = vec![
+            ("grant_type", "authorization_code"),
+            ("client_id", &config.client_id),
+            ("client_secret", &config.client_secret),
             ("code", &self.code),
             ("state", &self.state),
+            ("code_verifier", &self.code_verifier),
         ];
-        let result = client.post("https://github.com/login/oauth/access_token")
+        if let Some(redirect_uri) = &config.redirect_uri {
+            parameters.push(("redirect_uri", redirect_uri));
+        }
+        let result = client.post(&self.provider.endpoints().token_url)
             .header("Accept", "application/json")
             .form(&parameters)
             .send()
@@ -160,41 +301,7 @@ impl<'a> TokenRequest<'a> {
 }
 
 #[derive(Deserialize)]
-struct TokenResponse {
-    access_token: String,
-}
-
-struct UserRequest {
-    access_token: String,
-}
-
-impl UserRequest {
-    fn new(access_token: String) -> Self {
-        Self {
-            access_token: access_token,
-        }
-    }
-
-    async fn execute(&self) -> Result<UserResponse, AuthenticationError> {
-        let client = reqwest::Client::new();
-        let response = client.get("https://api.github.com/user")
-            .header("Accept", "application/vnd.github.v3+json")
-            .header("Authorization", format!("token {}", self.access_token))
-            .header("User-Agent", "Webauthexp")
-            .send()
-            .await
-            .or(Err(AuthenticationError::UserRequestFailed))?;
-
-        let result = response.json::<UserResponse>()
-            .await
-            .or(Err(AuthenticationError::InvalidUserResponse))?;
-        Ok(result)
-    }
-}
-
-#[derive(Deserialize)]
-struct UserResponse {
-    id: u64,
-    login: String,
-    name: String,
+pub struct TokenResponse {
+    pub access_token: String,
+    pub id_token: Option<String>,
 }
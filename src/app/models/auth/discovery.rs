@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use jsonwebtoken::jwk::JwkSet;
+use serde_derive::Deserialize;
+
+use super::AuthenticationError;
+
+const METADATA_TTL: Duration = Duration::from_secs(3600);
+
+#[derive(Clone, Deserialize)]
+pub struct OpenIdConfiguration {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub userinfo_endpoint: Option<String>,
+    pub jwks_uri: String,
+}
+
+pub struct ProviderMetadata {
+    pub configuration: OpenIdConfiguration,
+    pub jwks: JwkSet,
+    fetched_at: Instant,
+}
+
+impl ProviderMetadata {
+    fn is_fresh(&self) -> bool {
+        self.fetched_at.elapsed() < METADATA_TTL
+    }
+}
+
+/// Caches OpenID provider metadata and signing keys per issuer, so that
+/// discovery and JWKS fetches only happen once an hour or on key rotation.
+#[derive(Clone, Default)]
+pub struct DiscoveryCache {
+    entries: Arc<RwLock<HashMap<String, Arc<ProviderMetadata>>>>,
+}
+
+impl DiscoveryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn discover(&self, issuer: &str) -> Result<Arc<ProviderMetadata>, AuthenticationError> {
+        if let Some(metadata) = self.cached(issuer) {
+            if metadata.is_fresh() {
+                return Ok(metadata)
+            }
+        }
+        let configuration = Self::fetch_configuration(issuer).await?;
+        self.store(issuer, configuration).await
+    }
+
+    /// Refetches the JWKS of an issuer, used when an ID token is signed
+    /// with a key that is not in the cached set.
+    pub async fn refresh_keys(&self, issuer: &str) -> Result<Arc<ProviderMetadata>, AuthenticationError> {
+        let configuration = match self.cached(issuer) {
+            Some(metadata) => metadata.configuration.clone(),
+            None => Self::fetch_configuration(issuer).await?,
+        };
+        self.store(issuer, configuration).await
+    }
+
+    fn cached(&self, issuer: &str) -> Option<Arc<ProviderMetadata>> {
+        let entries = self.entries.read().ok()?;
+        entries.get(issuer).cloned()
+    }
+
+    async fn store(&self, issuer: &str, configuration: OpenIdConfiguration) -> Result<Arc<ProviderMetadata>, AuthenticationError> {
+        let jwks = Self::fetch_keys(&configuration.jwks_uri).await?;
+        let metadata = Arc::new(ProviderMetadata {
+            configuration,
+            jwks,
+            fetched_at: Instant::now(),
+        });
+        if let Ok(mut entries) = self.entries.write() {
+            entries.insert(issuer.to_owned(), metadata.clone());
+        }
+        Ok(metadata)
+    }
+
+    async fn fetch_configuration(issuer: &str) -> Result<OpenIdConfiguration, AuthenticationError> {
+        let url = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
+        let configuration = reqwest::get(&url)
+            .await
+            .and_then(|response| response.error_for_status())
+            .or(Err(AuthenticationError::DiscoveryFailed))?
+            .json::<OpenIdConfiguration>()
+            .await
+            .or(Err(AuthenticationError::DiscoveryFailed))?;
+
+        if configuration.issuer != issuer {
+            return Err(AuthenticationError::DiscoveryFailed)
+        }
+        Ok(configuration)
+    }
+
+    async fn fetch_keys(jwks_uri: &str) -> Result<JwkSet, AuthenticationError> {
+        let jwks = reqwest::get(jwks_uri)
+            .await
+            .and_then(|response| response.error_for_status())
+            .or(Err(AuthenticationError::DiscoveryFailed))?
+            .json::<JwkSet>()
+            .await
+            .or(Err(AuthenticationError::DiscoveryFailed))?;
+        Ok(jwks)
+    }
+}
@@ -0,0 +1,76 @@
+use futures_util::future::{FutureExt as _, LocalBoxFuture};
+use serde_derive::Deserialize;
+
+use crate::app::config::ProviderConfig;
+use super::{AuthenticationError, TokenResponse};
+use super::provider::{IdentityProvider, ProviderEndpoints, ProviderUser};
+
+pub struct GitHubProvider {
+    name: String,
+    config: ProviderConfig,
+    endpoints: ProviderEndpoints,
+}
+
+impl GitHubProvider {
+    pub fn new(name: &str, config: &ProviderConfig) -> Self {
+        let endpoints = ProviderEndpoints {
+            authorize_url: config.authorize_url.clone().unwrap_or_else(|| "https://github.com/login/oauth/authorize".to_owned()),
+            token_url: config.token_url.clone().unwrap_or_else(|| "https://github.com/login/oauth/access_token".to_owned()),
+            user_url: config.user_url.clone().unwrap_or_else(|| "https://api.github.com/user".to_owned()),
+            scope: config.scope.clone().unwrap_or_else(|| "read:user".to_owned()),
+        };
+        Self {
+            name: name.to_owned(),
+            config: config.clone(),
+            endpoints,
+        }
+    }
+
+    async fn request_user(&self, access_token: &str) -> Result<ProviderUser, AuthenticationError> {
+        let client = reqwest::Client::new();
+        let response = client.get(&self.endpoints.user_url)
+            .header("Accept", "application/vnd.github.v3+json")
+            .header("Authorization", format!("token {}", access_token))
+            .header("User-Agent", "Webauthexp")
+            .send()
+            .await
+            .or(Err(AuthenticationError::UserRequestFailed))?;
+
+        let user = response.json::<GitHubUser>()
+            .await
+            .or(Err(AuthenticationError::InvalidUserResponse))?;
+        let result = ProviderUser {
+            id: user.id.to_string(),
+            login: user.login,
+            name: user.name,
+            avatar_url: user.avatar_url,
+        };
+        Ok(result)
+    }
+}
+
+impl IdentityProvider for GitHubProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    fn endpoints(&self) -> &ProviderEndpoints {
+        &self.endpoints
+    }
+
+    fn fetch_user<'a>(&'a self, tokens: &'a TokenResponse, _nonce: Option<&'a str>) -> LocalBoxFuture<'a, Result<ProviderUser, AuthenticationError>> {
+        self.request_user(&tokens.access_token).boxed_local()
+    }
+}
+
+#[derive(Deserialize)]
+struct GitHubUser {
+    id: u64,
+    login: String,
+    name: Option<String>,
+    avatar_url: Option<String>,
+}
@@ -0,0 +1,78 @@
+use futures_util::future::{FutureExt as _, LocalBoxFuture};
+use serde_derive::Deserialize;
+
+use crate::app::config::ProviderConfig;
+use super::{AuthenticationError, TokenResponse};
+use super::provider::{IdentityProvider, ProviderEndpoints, ProviderUser};
+
+pub struct GitLabProvider {
+    name: String,
+    config: ProviderConfig,
+    endpoints: ProviderEndpoints,
+}
+
+impl GitLabProvider {
+    pub fn new(name: &str, config: &ProviderConfig) -> Self {
+        let base_url = config.base_url.as_deref()
+            .unwrap_or("https://gitlab.com")
+            .trim_end_matches('/');
+        let endpoints = ProviderEndpoints {
+            authorize_url: config.authorize_url.clone().unwrap_or_else(|| format!("{}/oauth/authorize", base_url)),
+            token_url: config.token_url.clone().unwrap_or_else(|| format!("{}/oauth/token", base_url)),
+            user_url: config.user_url.clone().unwrap_or_else(|| format!("{}/api/v4/user", base_url)),
+            scope: config.scope.clone().unwrap_or_else(|| "read_user".to_owned()),
+        };
+        Self {
+            name: name.to_owned(),
+            config: config.clone(),
+            endpoints,
+        }
+    }
+
+    async fn request_user(&self, access_token: &str) -> Result<ProviderUser, AuthenticationError> {
+        let client = reqwest::Client::new();
+        let response = client.get(&self.endpoints.user_url)
+            .header("Accept", "application/json")
+            .bearer_auth(access_token)
+            .send()
+            .await
+            .or(Err(AuthenticationError::UserRequestFailed))?;
+
+        let user = response.json::<GitLabUser>()
+            .await
+            .or(Err(AuthenticationError::InvalidUserResponse))?;
+        let result = ProviderUser {
+            id: user.id.to_string(),
+            login: user.username,
+            name: user.name,
+            avatar_url: user.avatar_url,
+        };
+        Ok(result)
+    }
+}
+
+impl IdentityProvider for GitLabProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    fn endpoints(&self) -> &ProviderEndpoints {
+        &self.endpoints
+    }
+
+    fn fetch_user<'a>(&'a self, tokens: &'a TokenResponse, _nonce: Option<&'a str>) -> LocalBoxFuture<'a, Result<ProviderUser, AuthenticationError>> {
+        self.request_user(&tokens.access_token).boxed_local()
+    }
+}
+
+#[derive(Deserialize)]
+struct GitLabUser {
+    id: u64,
+    username: String,
+    name: Option<String>,
+    avatar_url: Option<String>,
+}
@@ -0,0 +1,133 @@
+use std::sync::Arc;
+
+use futures_util::future::{FutureExt as _, LocalBoxFuture};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::jwk::{AlgorithmParameters, JwkSet};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use serde_derive::Deserialize;
+
+use crate::app::config::ProviderConfig;
+use super::{AuthenticationError, TokenResponse};
+use super::discovery::{DiscoveryCache, ProviderMetadata};
+use super::provider::{IdentityProvider, ProviderEndpoints, ProviderUser};
+
+const ALLOWED_ALGORITHMS: [Algorithm; 9] = [
+    Algorithm::RS256, Algorithm::RS384, Algorithm::RS512,
+    Algorithm::PS256, Algorithm::PS384, Algorithm::PS512,
+    Algorithm::ES256, Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+pub struct OidcProvider {
+    name: String,
+    config: ProviderConfig,
+    endpoints: ProviderEndpoints,
+    issuer: String,
+    metadata: Arc<ProviderMetadata>,
+    discovery: DiscoveryCache,
+}
+
+impl OidcProvider {
+    pub async fn discover(name: &str, config: &ProviderConfig, discovery: &DiscoveryCache) -> Result<Self, AuthenticationError> {
+        let issuer = config.issuer.clone().ok_or(AuthenticationError::ProviderMisconfigured)?;
+        let metadata = discovery.discover(&issuer).await?;
+        let discovered = &metadata.configuration;
+        let endpoints = ProviderEndpoints {
+            authorize_url: config.authorize_url.clone().unwrap_or_else(|| discovered.authorization_endpoint.clone()),
+            token_url: config.token_url.clone().unwrap_or_else(|| discovered.token_endpoint.clone()),
+            user_url: config.user_url.clone().or_else(|| discovered.userinfo_endpoint.clone()).unwrap_or_default(),
+            scope: config.scope.clone().unwrap_or_else(|| "openid profile".to_owned()),
+        };
+        let provider = Self {
+            name: name.to_owned(),
+            config: config.clone(),
+            endpoints,
+            issuer,
+            metadata: metadata.clone(),
+            discovery: discovery.clone(),
+        };
+        Ok(provider)
+    }
+
+    async fn verify_id_token(&self, tokens: &TokenResponse, nonce: Option<&str>) -> Result<ProviderUser, AuthenticationError> {
+        let id_token = tokens.id_token.as_deref().ok_or(AuthenticationError::IdTokenMissing)?;
+        let header = jsonwebtoken::decode_header(id_token).or(Err(AuthenticationError::InvalidIdToken))?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(AuthenticationError::InvalidIdToken)
+        }
+
+        let key = match Self::find_key(&self.metadata.jwks, header.kid.as_deref()) {
+            Some(key) => key,
+            None => {
+                let metadata = self.discovery.refresh_keys(&self.issuer).await?;
+                Self::find_key(&metadata.jwks, header.kid.as_deref())
+                    .ok_or(AuthenticationError::SigningKeyNotFound)?
+            }
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+        let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|error| match error.kind() {
+                ErrorKind::InvalidSignature => AuthenticationError::InvalidIdTokenSignature,
+                ErrorKind::InvalidIssuer => AuthenticationError::IssuerNotMatch,
+                ErrorKind::InvalidAudience => AuthenticationError::AudienceNotMatch,
+                ErrorKind::ExpiredSignature => AuthenticationError::IdTokenExpired,
+                _ => AuthenticationError::InvalidIdToken,
+            })?
+            .claims;
+
+        if nonce.is_none() || claims.nonce.as_deref() != nonce {
+            return Err(AuthenticationError::NonceNotMatch)
+        }
+
+        let user = ProviderUser {
+            login: claims.preferred_username.unwrap_or_else(|| claims.sub.clone()),
+            id: claims.sub,
+            name: claims.name,
+            avatar_url: claims.picture,
+        };
+        Ok(user)
+    }
+
+    fn find_key(jwks: &JwkSet, kid: Option<&str>) -> Option<DecodingKey> {
+        let jwk = match kid {
+            Some(kid) => jwks.find(kid)?,
+            None if jwks.keys.len() == 1 => &jwks.keys[0],
+            None => return None,
+        };
+        if let AlgorithmParameters::OctetKey(_) = jwk.algorithm {
+            return None
+        }
+        DecodingKey::from_jwk(jwk).ok()
+    }
+}
+
+impl IdentityProvider for OidcProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn config(&self) -> &ProviderConfig {
+        &self.config
+    }
+
+    fn endpoints(&self) -> &ProviderEndpoints {
+        &self.endpoints
+    }
+
+    fn fetch_user<'a>(&'a self, tokens: &'a TokenResponse, nonce: Option<&'a str>) -> LocalBoxFuture<'a, Result<ProviderUser, AuthenticationError>> {
+        self.verify_id_token(tokens, nonce).boxed_local()
+    }
+}
+
+#[derive(Deserialize)]
+struct IdTokenClaims {
+    sub: String,
+    nonce: Option<String>,
+    preferred_username: Option<String>,
+    name: Option<String>,
+    picture: Option<String>,
+}
@@ -0,0 +1,50 @@
+use futures_util::future::LocalBoxFuture;
+
+use crate::app::config::{ProviderConfig, ProviderKind};
+use crate::app::context::Context;
+use super::{AuthenticationError, TokenResponse};
+use super::github::GitHubProvider;
+use super::gitlab::GitLabProvider;
+use super::oidc::OidcProvider;
+
+pub struct ProviderEndpoints {
+    pub authorize_url: String,
+    pub token_url: String,
+    pub user_url: String,
+    pub scope: String,
+}
+
+pub struct ProviderUser {
+    pub id: String,
+    pub login: String,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+pub trait IdentityProvider {
+    fn name(&self) -> &str;
+
+    fn config(&self) -> &ProviderConfig;
+
+    fn endpoints(&self) -> &ProviderEndpoints;
+
+    /// Resolves the signed-in user from the token response. `nonce` is the
+    /// value sent with the authorization request, checked by providers that
+    /// issue ID tokens.
+    fn fetch_user<'a>(&'a self, tokens: &'a TokenResponse, nonce: Option<&'a str>) -> LocalBoxFuture<'a, Result<ProviderUser, AuthenticationError>>;
+
+    fn provider_identifier(&self, user: &ProviderUser) -> String {
+        format!("{}:{}", self.name(), user.id)
+    }
+}
+
+pub async fn find_provider(context: &Context, name: &str) -> Result<Box<dyn IdentityProvider>, AuthenticationError> {
+    let provider_config = context.config.auth.provider(name)
+        .ok_or(AuthenticationError::UnknownProvider)?;
+    let provider: Box<dyn IdentityProvider> = match provider_config.kind {
+        ProviderKind::Github => Box::new(GitHubProvider::new(name, provider_config)),
+        ProviderKind::Gitlab => Box::new(GitLabProvider::new(name, provider_config)),
+        ProviderKind::Oidc => Box::new(OidcProvider::discover(name, provider_config, &context.discovery).await?),
+    };
+    Ok(provider)
+}
@@ -0,0 +1,77 @@
+use reqwest::Url;
+
+use crate::app::config::FrontendConfig;
+use super::AuthenticationError;
+
+/// Resolves where to send the browser after a redirect-based sign-in.
+/// Relative paths are joined to the frontend base URI; absolute URLs must
+/// share an origin with the base URI or one of `allowed_return_origins`.
+pub fn resolve_return_to(config: &FrontendConfig, return_to: Option<&str>) -> Result<String, AuthenticationError> {
+    let return_to = match return_to {
+        Some(return_to) if !return_to.is_empty() => return_to,
+        _ => return Ok(config.base_uri.clone()),
+    };
+
+    if return_to.starts_with('/') && !return_to.starts_with("//") && !return_to.contains('\\') {
+        return Ok(format!("{}{}", config.base_uri.trim_end_matches('/'), return_to))
+    }
+
+    let url = Url::parse(return_to).or(Err(AuthenticationError::ReturnToNotAllowed))?;
+    let origin = url.origin();
+    let allowed = std::iter::once(&config.base_uri)
+        .chain(config.allowed_return_origins.iter())
+        .filter_map(|allowed| Url::parse(allowed).ok())
+        .any(|allowed| allowed.origin() == origin);
+    if !allowed {
+        return Err(AuthenticationError::ReturnToNotAllowed)
+    }
+    Ok(url.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frontend() -> FrontendConfig {
+        FrontendConfig {
+            base_uri: "http://localhost:3000/".to_owned(),
+            allowed_return_origins: vec!["https://app.example.com".to_owned()],
+        }
+    }
+
+    #[test]
+    fn defaults_to_the_frontend() {
+        assert_eq!(resolve_return_to(&frontend(), None).unwrap(), "http://localhost:3000/");
+        assert_eq!(resolve_return_to(&frontend(), Some("")).unwrap(), "http://localhost:3000/");
+    }
+
+    #[test]
+    fn joins_relative_paths() {
+        assert_eq!(resolve_return_to(&frontend(), Some("/servants?page=2")).unwrap(), "http://localhost:3000/servants?page=2");
+    }
+
+    #[test]
+    fn accepts_allowed_origins() {
+        assert_eq!(resolve_return_to(&frontend(), Some("http://localhost:3000/me")).unwrap(), "http://localhost:3000/me");
+        assert_eq!(resolve_return_to(&frontend(), Some("https://app.example.com/done")).unwrap(), "https://app.example.com/done");
+    }
+
+    #[test]
+    fn rejects_other_targets() {
+        for return_to in [
+            "https://evil.example.com/",
+            "http://app.example.com/",
+            "https://app.example.com.evil.example.com/",
+            "//evil.example.com/",
+            "/\\evil.example.com",
+            "servants",
+            "javascript:alert(1)",
+        ] {
+            assert!(
+                matches!(resolve_return_to(&frontend(), Some(return_to)), Err(AuthenticationError::ReturnToNotAllowed)),
+                "{} should be rejected",
+                return_to,
+            );
+        }
+    }
+}
@@ -0,0 +1,7 @@
+pub use crate::app::db::credential_repository::LinkedCredential;
+
+mod listing;
+pub use listing::CredentialListing;
+
+mod unlinking;
+pub use unlinking::CredentialUnlinking;
@@ -0,0 +1,25 @@
+use crate::app::context::Context;
+use crate::app::db::credential_repository::CredentialRepository;
+use crate::app::models::DomainError;
+use super::LinkedCredential;
+
+pub struct CredentialListing<'a> {
+    context: &'a Context,
+    identity_id: String,
+}
+
+impl<'a> CredentialListing<'a> {
+    pub fn new(context: &'a Context, identity_id: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<Vec<LinkedCredential>, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = CredentialRepository::new(&connection);
+        let credentials = repository.list(&self.identity_id).await?;
+        Ok(credentials)
+    }
+}
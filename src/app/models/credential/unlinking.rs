@@ -0,0 +1,29 @@
+use crate::app::context::Context;
+use crate::app::db::credential_repository::CredentialRepository;
+use crate::app::models::DomainError;
+use super::LinkedCredential;
+
+pub struct CredentialUnlinking<'a> {
+    context: &'a Context,
+    identity_id: String,
+    id: String,
+}
+
+impl<'a> CredentialUnlinking<'a> {
+    pub fn new(context: &'a Context, identity_id: &str, id: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+            id: id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<LinkedCredential, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = CredentialRepository::new(&connection);
+        repository.show(&self.identity_id, &self.id).await?;
+        let credential = repository.delete_unless_last(&self.identity_id, &self.id).await?
+            .ok_or(DomainError::LastCredential)?;
+        Ok(credential)
+    }
+}
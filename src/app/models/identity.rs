@@ -1,10 +1,65 @@
+use std::fmt;
+use std::str::FromStr;
+
 use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
 use tokio_pg_mapper_derive::PostgresMapper;
 
-#[derive(Deserialize, PostgresMapper, Serialize)]
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Admin,
+}
+
+#[derive(Debug, Error)]
+#[error("Unknown role: {0}")]
+pub struct UnknownRole(pub String);
+
+impl Role {
+    pub const ALL: [Role; 2] = [Self::User, Self::Admin];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = UnknownRole;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter()
+            .find(|role| role.as_str() == s)
+            .copied()
+            .ok_or_else(|| UnknownRole(s.to_owned()))
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Clone, Deserialize, PostgresMapper, Serialize)]
 #[pg_mapper(table = "identities")]
 pub struct Identity {
     pub id: String,
-    pub provider_identifier: String,
     pub alive: bool,
+    pub role: Role,
+    pub login: Option<String>,
+    pub name: Option<String>,
+    pub avatar_url: Option<String>,
 }
+
+mod deactivation;
+pub use deactivation::IdentityDeactivation;
+
+mod listing;
+pub use listing::IdentityListing;
+
+mod status;
+pub use status::IdentityStatusChange;
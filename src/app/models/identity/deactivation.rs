@@ -0,0 +1,25 @@
+use crate::app::context::Context;
+use crate::app::db::identity_repository::IdentityRepository;
+use crate::app::models::DomainError;
+use super::Identity;
+
+pub struct IdentityDeactivation<'a> {
+    context: &'a Context,
+    id: String,
+}
+
+impl<'a> IdentityDeactivation<'a> {
+    pub fn new(context: &'a Context, id: &str) -> Self {
+        Self {
+            context,
+            id: id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<Identity, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = IdentityRepository::new(&connection);
+        let identity = repository.set_alive(&self.id, false).await?;
+        Ok(identity)
+    }
+}
@@ -0,0 +1,23 @@
+use crate::app::context::Context;
+use crate::app::db::identity_repository::IdentityRepository;
+use crate::app::models::DomainError;
+use super::Identity;
+
+pub struct IdentityListing<'a> {
+    context: &'a Context,
+}
+
+impl<'a> IdentityListing<'a> {
+    pub fn new(context: &'a Context) -> Self {
+        Self {
+            context,
+        }
+    }
+
+    pub async fn execute(&self) -> Result<Vec<Identity>, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = IdentityRepository::new(&connection);
+        let identities = repository.list().await?;
+        Ok(identities)
+    }
+}
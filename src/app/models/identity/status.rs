@@ -0,0 +1,27 @@
+use crate::app::context::Context;
+use crate::app::db::identity_repository::IdentityRepository;
+use crate::app::models::DomainError;
+use super::Identity;
+
+pub struct IdentityStatusChange<'a> {
+    context: &'a Context,
+    id: String,
+    alive: bool,
+}
+
+impl<'a> IdentityStatusChange<'a> {
+    pub fn new(context: &'a Context, id: &str, alive: bool) -> Self {
+        Self {
+            context,
+            id: id.to_owned(),
+            alive,
+        }
+    }
+
+    pub async fn execute(&self) -> Result<Identity, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = IdentityRepository::new(&connection);
+        let identity = repository.set_alive(&self.id, self.alive).await?;
+        Ok(identity)
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum Scope {
+    #[serde(rename = "servants:read")]
+    ServantsRead,
+    #[serde(rename = "servants:write")]
+    ServantsWrite,
+}
+
+#[derive(Debug, Error)]
+#[error("Unknown scope: {0}")]
+pub struct UnknownScope(pub String);
+
+impl Scope {
+    pub const ALL: [Scope; 2] = [
+        Self::ServantsRead,
+        Self::ServantsWrite,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ServantsRead => "servants:read",
+            Self::ServantsWrite => "servants:write",
+        }
+    }
+
+    pub fn allowed_values() -> Vec<&'static str> {
+        Self::ALL.iter().map(|scope| scope.as_str()).collect()
+    }
+}
+
+impl FromStr for Scope {
+    type Err = UnknownScope;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter()
+            .find(|scope| scope.as_str() == s)
+            .copied()
+            .ok_or_else(|| UnknownScope(s.to_owned()))
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
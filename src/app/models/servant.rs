@@ -1,8 +1,14 @@
+use std::fmt;
+use std::str::FromStr;
+
 use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
 
 pub use crate::app::db::servant_repository::Servant;
 
-#[derive(Debug, Deserialize, Serialize)]
+pub const NAME_MAX_LENGTH: usize = 100;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ServantClass {
     Saber,
@@ -21,14 +27,80 @@ pub enum ServantClass {
     Shielder,
 }
 
+#[derive(Debug, Error)]
+#[error("Unknown servant class: {0}")]
+pub struct UnknownServantClass(pub String);
+
+impl ServantClass {
+    pub const ALL: [ServantClass; 14] = [
+        Self::Saber,
+        Self::Archer,
+        Self::Lancer,
+        Self::Rider,
+        Self::Caster,
+        Self::Assassin,
+        Self::Berserker,
+        Self::Ruler,
+        Self::Avenger,
+        Self::Mooncancer,
+        Self::Alterego,
+        Self::Foreigner,
+        Self::Pretender,
+        Self::Shielder,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Saber => "saber",
+            Self::Archer => "archer",
+            Self::Lancer => "lancer",
+            Self::Rider => "rider",
+            Self::Caster => "caster",
+            Self::Assassin => "assassin",
+            Self::Berserker => "berserker",
+            Self::Ruler => "ruler",
+            Self::Avenger => "avenger",
+            Self::Mooncancer => "mooncancer",
+            Self::Alterego => "alterego",
+            Self::Foreigner => "foreigner",
+            Self::Pretender => "pretender",
+            Self::Shielder => "shielder",
+        }
+    }
+
+    pub fn allowed_values() -> Vec<&'static str> {
+        Self::ALL.iter().map(|class| class.as_str()).collect()
+    }
+}
+
+impl FromStr for ServantClass {
+    type Err = UnknownServantClass;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter()
+            .find(|class| class.as_str() == s)
+            .copied()
+            .ok_or_else(|| UnknownServantClass(s.to_owned()))
+    }
+}
+
+impl fmt::Display for ServantClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 mod registration;
 pub use registration::ServantRegistration;
 
-mod listing;
-pub use listing::ServantListing;
+pub mod listing;
+pub use listing::{ServantCursor, ServantListing};
 
 mod fetching;
 pub use fetching::ServantFetching;
 
+mod update;
+pub use update::ServantUpdate;
+
 mod deletion;
 pub use deletion::ServantDeletion;
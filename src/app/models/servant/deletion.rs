@@ -5,21 +5,31 @@ use super::Servant;
 
 pub struct ServantDeletion<'a> {
     context: &'a Context,
+    owner_id: Option<String>,
     id: i32,
 }
 
 impl<'a> ServantDeletion<'a> {
-    pub fn new(context: &'a Context, id: i32) -> Self {
+    pub fn new(context: &'a Context, owner_id: &str, id: i32) -> Self {
         Self {
-            context: context,
-            id: id,
+            context,
+            owner_id: Some(owner_id.to_owned()),
+            id,
+        }
+    }
+
+    pub fn regardless_of_owner(context: &'a Context, id: i32) -> Self {
+        Self {
+            context,
+            owner_id: None,
+            id,
         }
     }
 
     pub async fn execute(&self) -> Result<Servant, DomainError> {
         let connection = self.context.db.establish_connection().await?;
         let repository = ServantRepository::new(&connection);
-        let servant = repository.delete(self.id).await?;
+        let servant = repository.delete(self.owner_id.as_deref(), self.id).await?;
         Ok(servant)
     }
 }
@@ -4,21 +4,31 @@ use crate::app::models::DomainError;
 use super::Servant;
 pub struct ServantFetching<'a> {
     context: &'a Context,
+    owner_id: Option<String>,
     id: i32,
 }
 
 impl<'a> ServantFetching<'a> {
-    pub fn new(context: &'a Context, id: i32) -> Self {
+    pub fn new(context: &'a Context, owner_id: &str, id: i32) -> Self {
         Self {
-            context: context,
-            id: id,
+            context,
+            owner_id: Some(owner_id.to_owned()),
+            id,
+        }
+    }
+
+    pub fn regardless_of_owner(context: &'a Context, id: i32) -> Self {
+        Self {
+            context,
+            owner_id: None,
+            id,
         }
     }
 
     pub async fn execute(&self) -> Result<Servant, DomainError> {
         let connection = self.context.db.establish_connection().await?;
         let repository = ServantRepository::new(&connection);
-        let servants = repository.show(self.id).await?;
+        let servants = repository.show(self.owner_id.as_deref(), self.id).await?;
         Ok(servants)
     }
 }
@@ -1,23 +1,281 @@
+use std::str::FromStr;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
+
 use crate::app::context::Context;
-use crate::app::db::servant_repository::ServantRepository;
+use crate::app::db::servant_repository::{ListingDataset, ListingPosition, ServantRepository};
 use crate::app::models::DomainError;
-use super::Servant;
+use super::{Servant, ServantClass};
+
+pub const DEFAULT_LIMIT: i64 = 20;
+pub const MAX_LIMIT: i64 = 100;
+
+#[derive(Debug, Error)]
+#[error("Malformed cursor")]
+pub struct MalformedCursor;
+
+#[derive(Debug, Error)]
+#[error("Unknown sort parameter: {0}")]
+pub struct UnknownSortParameter(pub String);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortColumn {
+    Id,
+    Name,
+    ClassName,
+}
+
+impl SortColumn {
+    pub const ALL: [SortColumn; 3] = [Self::Id, Self::Name, Self::ClassName];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Name => "name",
+            Self::ClassName => "class_name",
+        }
+    }
+
+    pub fn column_name(&self) -> &'static str {
+        self.as_str()
+    }
+
+    pub fn allowed_values() -> Vec<&'static str> {
+        Self::ALL.iter().map(|column| column.as_str()).collect()
+    }
+
+    fn key_of(&self, servant: &Servant) -> String {
+        match self {
+            Self::Id => servant.id().to_string(),
+            Self::Name => servant.name().to_owned(),
+            Self::ClassName => servant.class_name().as_str().to_owned(),
+        }
+    }
+}
+
+impl FromStr for SortColumn {
+    type Err = UnknownSortParameter;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter()
+            .find(|column| column.as_str() == s)
+            .copied()
+            .ok_or_else(|| UnknownSortParameter(s.to_owned()))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Asc => "asc",
+            Self::Desc => "desc",
+        }
+    }
+
+    pub fn allowed_values() -> Vec<&'static str> {
+        vec!["asc", "desc"]
+    }
+}
+
+impl FromStr for SortOrder {
+    type Err = UnknownSortParameter;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "asc" => Ok(Self::Asc),
+            "desc" => Ok(Self::Desc),
+            _ => Err(UnknownSortParameter(s.to_owned())),
+        }
+    }
+}
+
+/// Position after the last servant of a page. It records the sort it was
+/// issued under, since its key is only meaningful as a bound on that column.
+#[derive(Deserialize, Serialize)]
+pub struct ServantCursor {
+    id: i32,
+    key: String,
+    sort: String,
+    order: String,
+}
+
+impl ServantCursor {
+    fn new(servant: &Servant, sort: &ServantSort) -> Self {
+        Self {
+            id: servant.id(),
+            key: sort.column.key_of(servant),
+            sort: sort.column.as_str().to_owned(),
+            order: sort.order.as_str().to_owned(),
+        }
+    }
+
+    pub fn matches(&self, sort: &ServantSort) -> bool {
+        self.sort == sort.column.as_str() && self.order == sort.order.as_str()
+    }
+
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        URL_SAFE_NO_PAD.encode(json)
+    }
+}
+
+impl FromStr for ServantCursor {
+    type Err = MalformedCursor;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = URL_SAFE_NO_PAD.decode(s).or(Err(MalformedCursor))?;
+        serde_json::from_slice(&bytes).or(Err(MalformedCursor))
+    }
+}
+
+pub struct ServantFilter {
+    pub classes: Vec<ServantClass>,
+    pub name_query: Option<String>,
+}
+
+pub struct ServantSort {
+    pub column: SortColumn,
+    pub order: SortOrder,
+}
+
+impl Default for ServantSort {
+    fn default() -> Self {
+        Self {
+            column: SortColumn::Id,
+            order: SortOrder::Asc,
+        }
+    }
+}
+
+pub struct ServantPage {
+    pub servants: Vec<Servant>,
+    pub next_cursor: Option<ServantCursor>,
+}
 
 pub struct ServantListing<'a> {
     context: &'a Context,
+    owner_id: String,
+    filter: ServantFilter,
+    sort: ServantSort,
+    limit: i64,
+    after: Option<ServantCursor>,
 }
 
 impl<'a> ServantListing<'a> {
-    pub fn new(context: &'a Context) -> Self {
+    pub fn new(context: &'a Context, owner_id: &str, filter: ServantFilter, sort: ServantSort, limit: i64, after: Option<ServantCursor>) -> Self {
         Self {
-            context: context,
+            context,
+            owner_id: owner_id.to_owned(),
+            filter,
+            sort,
+            limit,
+            after,
         }
     }
 
-    pub async fn execute(&self) -> Result<Vec<Servant>, DomainError> {
+    pub async fn execute(self) -> Result<ServantPage, DomainError> {
         let connection = self.context.db.establish_connection().await?;
         let repository = ServantRepository::new(&connection);
-        let servants = repository.list().await?;
-        Ok(servants)
+
+        let classes = match self.filter.classes.is_empty() {
+            true => None,
+            false => Some(self.filter.classes.iter().map(|class| class.as_str()).collect()),
+        };
+        let dataset = ListingDataset {
+            owner_id: self.owner_id,
+            limit: self.limit + 1,
+            classes,
+            name_pattern: self.filter.name_query.as_deref().map(Self::contains_pattern),
+            sort_column: self.sort.column,
+            sort_order: self.sort.order,
+            after: self.after.map(|cursor| ListingPosition { id: cursor.id, key: cursor.key }),
+        };
+        let mut servants = repository.list(dataset).await?;
+
+        let has_more = servants.len() as i64 > self.limit;
+        servants.truncate(self.limit as usize);
+        let next_cursor = match servants.last() {
+            Some(servant) if has_more => Some(ServantCursor::new(servant, &self.sort)),
+            _ => None,
+        };
+
+        let page = ServantPage {
+            servants,
+            next_cursor,
+        };
+        Ok(page)
+    }
+
+    fn contains_pattern(query: &str) -> String {
+        let escaped = query
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        format!("%{}%", escaped)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_survives_a_round_trip() {
+        let cursor = ServantCursor { id: 42, key: "Mash Kyrielight".to_owned(), sort: "name".to_owned(), order: "desc".to_owned() };
+        let encoded = cursor.encode();
+        assert!(encoded.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+
+        let decoded: ServantCursor = encoded.parse().unwrap();
+        assert_eq!(decoded.id, 42);
+        assert_eq!(decoded.key, "Mash Kyrielight");
+        assert!(decoded.matches(&ServantSort { column: SortColumn::Name, order: SortOrder::Desc }));
+    }
+
+    #[test]
+    fn cursor_only_matches_the_sort_it_was_issued_under() {
+        let cursor = ServantCursor { id: 42, key: "Mash Kyrielight".to_owned(), sort: "name".to_owned(), order: "asc".to_owned() };
+        assert!(cursor.matches(&ServantSort { column: SortColumn::Name, order: SortOrder::Asc }));
+        assert!(!cursor.matches(&ServantSort { column: SortColumn::Name, order: SortOrder::Desc }));
+        assert!(!cursor.matches(&ServantSort { column: SortColumn::ClassName, order: SortOrder::Asc }));
+        assert!(!cursor.matches(&ServantSort::default()));
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        assert!("not base64!".parse::<ServantCursor>().is_err());
+        assert!(URL_SAFE_NO_PAD.encode("not json").parse::<ServantCursor>().is_err());
+        assert!(URL_SAFE_NO_PAD.encode(r#"{"id":"1"}"#).parse::<ServantCursor>().is_err());
+        assert!(URL_SAFE_NO_PAD.encode(r#"{"id":1,"key":"1"}"#).parse::<ServantCursor>().is_err());
+        assert!("".parse::<ServantCursor>().is_err());
+    }
+
+    #[test]
+    fn parses_sort_columns_and_orders() {
+        assert_eq!("id".parse::<SortColumn>().unwrap(), SortColumn::Id);
+        assert_eq!("name".parse::<SortColumn>().unwrap(), SortColumn::Name);
+        assert_eq!("class_name".parse::<SortColumn>().unwrap(), SortColumn::ClassName);
+        assert!("owner_id".parse::<SortColumn>().is_err());
+        assert!("Name".parse::<SortColumn>().is_err());
+        assert_eq!(SortColumn::allowed_values(), vec!["id", "name", "class_name"]);
+
+        assert_eq!("asc".parse::<SortOrder>().unwrap(), SortOrder::Asc);
+        assert_eq!("desc".parse::<SortOrder>().unwrap(), SortOrder::Desc);
+        assert!("descending".parse::<SortOrder>().is_err());
+    }
+
+    #[test]
+    fn escapes_like_wildcards_in_name_queries() {
+        assert_eq!(ServantListing::contains_pattern("Mash"), "%Mash%");
+        assert_eq!(ServantListing::contains_pattern("100%"), "%100\\%%");
+        assert_eq!(ServantListing::contains_pattern("a_b"), "%a\\_b%");
+        assert_eq!(ServantListing::contains_pattern("c:\\d"), "%c:\\\\d%");
     }
 }
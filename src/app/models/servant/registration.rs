@@ -1,20 +1,22 @@
 use crate::app::context::Context;
 use crate::app::db::servant_repository::{RegistrationDataset, ServantRepository};
 use crate::app::models::DomainError;
-use super::Servant;
+use super::{Servant, ServantClass};
 
 pub struct ServantRegistration<'a> {
     context: &'a Context,
+    owner_id: String,
     name: String,
-    class_name: String,
+    class_name: ServantClass,
 }
 
 impl<'a> ServantRegistration<'a> {
-    pub fn new(context: &'a Context, name: &str, class_name: &str) -> Self {
+    pub fn new(context: &'a Context, owner_id: &str, name: &str, class_name: ServantClass) -> Self {
       Self {
-          context: context,
+          context,
+          owner_id: owner_id.to_owned(),
           name: name.to_owned(),
-          class_name: class_name.to_owned(),
+          class_name,
       }
     }
 
@@ -22,6 +24,7 @@ impl<'a> ServantRegistration<'a> {
         let connection = self.context.db.establish_connection().await?;
         let repository = ServantRepository::new(&connection);
         let dataset = RegistrationDataset {
+            owner_id: self.owner_id,
             name: self.name,
             class_name: self.class_name,
         };
@@ -0,0 +1,35 @@
+use crate::app::context::Context;
+use crate::app::db::servant_repository::{ServantRepository, UpdateDataset};
+use crate::app::models::DomainError;
+use super::{Servant, ServantClass};
+
+pub struct ServantUpdate<'a> {
+    context: &'a Context,
+    owner_id: String,
+    id: i32,
+    name: Option<String>,
+    class_name: Option<ServantClass>,
+}
+
+impl<'a> ServantUpdate<'a> {
+    pub fn new(context: &'a Context, owner_id: &str, id: i32, name: Option<&str>, class_name: Option<ServantClass>) -> Self {
+        Self {
+            context,
+            owner_id: owner_id.to_owned(),
+            id,
+            name: name.map(|s| s.to_owned()),
+            class_name,
+        }
+    }
+
+    pub async fn execute(self) -> Result<Servant, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = ServantRepository::new(&connection);
+        let dataset = UpdateDataset {
+            name: self.name,
+            class_name: self.class_name,
+        };
+        let servant = repository.update(&self.owner_id, self.id, dataset).await?;
+        Ok(servant)
+    }
+}
@@ -0,0 +1,7 @@
+pub use crate::app::db::session_repository::ActiveSession;
+
+mod listing;
+pub use listing::SessionListing;
+
+mod revocation;
+pub use revocation::{SessionRevocation, SessionRevocationAll};
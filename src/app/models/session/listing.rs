@@ -0,0 +1,25 @@
+use crate::app::context::Context;
+use crate::app::db::session_repository::SessionRepository;
+use crate::app::models::DomainError;
+use super::ActiveSession;
+
+pub struct SessionListing<'a> {
+    context: &'a Context,
+    identity_id: String,
+}
+
+impl<'a> SessionListing<'a> {
+    pub fn new(context: &'a Context, identity_id: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<Vec<ActiveSession>, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = SessionRepository::new(&connection);
+        let sessions = repository.list(&self.identity_id).await?;
+        Ok(sessions)
+    }
+}
@@ -0,0 +1,48 @@
+use crate::app::context::Context;
+use crate::app::db::session_repository::SessionRepository;
+use crate::app::models::DomainError;
+use super::ActiveSession;
+
+pub struct SessionRevocation<'a> {
+    context: &'a Context,
+    identity_id: String,
+    id: String,
+}
+
+impl<'a> SessionRevocation<'a> {
+    pub fn new(context: &'a Context, identity_id: &str, id: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+            id: id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<ActiveSession, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = SessionRepository::new(&connection);
+        let session = repository.delete(&self.identity_id, &self.id).await?;
+        Ok(session)
+    }
+}
+
+pub struct SessionRevocationAll<'a> {
+    context: &'a Context,
+    identity_id: String,
+}
+
+impl<'a> SessionRevocationAll<'a> {
+    pub fn new(context: &'a Context, identity_id: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<u64, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = SessionRepository::new(&connection);
+        let count = repository.delete_all(&self.identity_id).await?;
+        Ok(count)
+    }
+}
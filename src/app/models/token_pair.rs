@@ -0,0 +1,140 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use jsonwebtoken::errors::ErrorKind;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::{RngExt, rngs::StdRng};
+use serde_derive::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::app::config::TokenConfig;
+use crate::app::context::Context;
+use crate::app::db::DatabaseError;
+use crate::app::db::refresh_token_repository::RefreshTokenRepository;
+use crate::app::models::access_token::hash_secret;
+
+mod issuance;
+pub use issuance::TokenPairIssuance;
+
+mod refresh;
+pub use refresh::TokenPairRefresh;
+
+mod revocation;
+pub use revocation::TokenPairRevocation;
+
+pub const ISSUER: &str = "actixexp";
+pub const AUDIENCE: &str = "actixexp-api";
+
+/// Prefix of refresh tokens, so they are never mistaken for access tokens.
+pub const REFRESH_TOKEN_PREFIX: &str = "axr_";
+
+#[derive(Debug, Error)]
+pub enum TokenError {
+    #[error("Token mode is not enabled")]
+    NotEnabled,
+
+    #[error("Token signing key is invalid")]
+    InvalidSigningKey,
+
+    #[error("Access token is invalid")]
+    InvalidAccessToken,
+
+    #[error("Access token is expired")]
+    AccessTokenExpired,
+
+    #[error("Refresh token is invalid or expired")]
+    InvalidRefreshToken,
+
+    #[error("Refresh token was already used; its token family is revoked")]
+    RefreshTokenReused,
+
+    #[error("Identity is deactivated")]
+    IdentityDeactivated,
+
+    #[error("Database error: {source}")]
+    DatabaseError {
+        #[from]
+        source: DatabaseError,
+    },
+}
+
+/// Claims of a signed access token. `sid` names the refresh token family
+/// the access token was issued with.
+#[derive(Deserialize, Serialize)]
+pub struct AccessClaims {
+    pub iss: String,
+    pub aud: String,
+    pub sub: String,
+    pub sid: String,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// What a token-mode sign-in or refresh hands back, in the shape of an
+/// OAuth token response.
+#[derive(Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+    pub refresh_token: String,
+}
+
+fn token_config(context: &Context) -> Result<&TokenConfig, TokenError> {
+    context.config.token.as_ref().ok_or(TokenError::NotEnabled)
+}
+
+/// Signs a fresh access token and stores a new refresh token in `family_id`,
+/// or in a new family when none is given.
+async fn issue(context: &Context, identity_id: &str, family_id: Option<&str>) -> Result<TokenPair, TokenError> {
+    let config = token_config(context)?;
+    let refresh_token = generate_refresh_token();
+    let connection = context.db.establish_connection().await?;
+    let repository = RefreshTokenRepository::new(&connection);
+    let family_id = repository.create(identity_id, family_id, &hash_secret(&refresh_token), config.refresh_ttl_days).await?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+    let claims = AccessClaims {
+        iss: ISSUER.to_owned(),
+        aud: AUDIENCE.to_owned(),
+        sub: identity_id.to_owned(),
+        sid: family_id,
+        iat: now,
+        exp: now + config.access_ttl_seconds,
+    };
+    let key = config.raw_signing_key().or(Err(TokenError::InvalidSigningKey))?;
+    let access_token = jsonwebtoken::encode(&Header::new(Algorithm::HS256), &claims, &EncodingKey::from_secret(&key))
+        .or(Err(TokenError::InvalidSigningKey))?;
+
+    let pair = TokenPair {
+        access_token,
+        token_type: "Bearer",
+        expires_in: config.access_ttl_seconds,
+        refresh_token,
+    };
+    Ok(pair)
+}
+
+/// Checks an access token's signature, issuer, audience and expiry.
+pub fn verify_access_token(context: &Context, token: &str) -> Result<AccessClaims, TokenError> {
+    let config = token_config(context)?;
+    let key = config.raw_signing_key().or(Err(TokenError::InvalidSigningKey))?;
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.set_issuer(&[ISSUER]);
+    validation.set_audience(&[AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+    let claims = jsonwebtoken::decode::<AccessClaims>(token, &DecodingKey::from_secret(&key), &validation)
+        .map_err(|error| match error.kind() {
+            ErrorKind::ExpiredSignature => TokenError::AccessTokenExpired,
+            _ => TokenError::InvalidAccessToken,
+        })?
+        .claims;
+    Ok(claims)
+}
+
+fn generate_refresh_token() -> String {
+    let mut rng: StdRng = rand::make_rng();
+    let mut rs: [u8; 32] = [0; 32];
+    rng.fill(&mut rs);
+    format!("{}{}", REFRESH_TOKEN_PREFIX, URL_SAFE_NO_PAD.encode(rs))
+}
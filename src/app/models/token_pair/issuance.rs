@@ -0,0 +1,21 @@
+use crate::app::context::Context;
+use super::{TokenError, TokenPair, issue};
+
+/// Starts a new refresh token family for a completed sign-in.
+pub struct TokenPairIssuance<'a> {
+    context: &'a Context,
+    identity_id: String,
+}
+
+impl<'a> TokenPairIssuance<'a> {
+    pub fn new(context: &'a Context, identity_id: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<TokenPair, TokenError> {
+        issue(self.context, &self.identity_id, None).await
+    }
+}
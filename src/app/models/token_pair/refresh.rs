@@ -0,0 +1,50 @@
+use crate::app::context::Context;
+use crate::app::db::DatabaseError;
+use crate::app::db::identity_repository::IdentityRepository;
+use crate::app::db::refresh_token_repository::RefreshTokenRepository;
+use crate::app::models::access_token::hash_secret;
+use super::{TokenError, TokenPair, issue, token_config};
+
+/// Exchanges a refresh token for a new pair. The presented token is spent;
+/// presenting it again revokes every token descended from the same sign-in,
+/// since one of the two holders must have stolen it.
+pub struct TokenPairRefresh<'a> {
+    context: &'a Context,
+    refresh_token: String,
+}
+
+impl<'a> TokenPairRefresh<'a> {
+    pub fn new(context: &'a Context, refresh_token: &str) -> Self {
+        Self {
+            context,
+            refresh_token: refresh_token.trim().to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<TokenPair, TokenError> {
+        token_config(self.context)?;
+        let token_hash = hash_secret(&self.refresh_token);
+        let connection = self.context.db.establish_connection().await?;
+        let repository = RefreshTokenRepository::new(&connection);
+        let grant = match repository.consume(&token_hash).await {
+            Ok(grant) => grant,
+            Err(DatabaseError::NotFound) => {
+                if let Some(family_id) = repository.find_reused_family(&token_hash).await? {
+                    repository.revoke_family(&family_id).await?;
+                    return Err(TokenError::RefreshTokenReused)
+                }
+                return Err(TokenError::InvalidRefreshToken)
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let identities = IdentityRepository::new(&connection);
+        let identity = identities.find_by_id(&grant.identity_id).await?;
+        if !identity.alive {
+            repository.revoke_family(&grant.family_id).await?;
+            return Err(TokenError::IdentityDeactivated)
+        }
+
+        issue(self.context, &identity.id, Some(&grant.family_id)).await
+    }
+}
@@ -0,0 +1,33 @@
+use crate::app::context::Context;
+use crate::app::db::DatabaseError;
+use crate::app::db::refresh_token_repository::RefreshTokenRepository;
+use crate::app::models::access_token::hash_secret;
+use super::TokenError;
+
+/// Signs a token-mode client out by revoking the refresh token's whole
+/// family. Access tokens already issued stay valid until they expire.
+pub struct TokenPairRevocation<'a> {
+    context: &'a Context,
+    refresh_token: String,
+}
+
+impl<'a> TokenPairRevocation<'a> {
+    pub fn new(context: &'a Context, refresh_token: &str) -> Self {
+        Self {
+            context,
+            refresh_token: refresh_token.trim().to_owned(),
+        }
+    }
+
+    /// Unknown tokens are not an error, so revocation cannot be used to
+    /// probe which tokens exist.
+    pub async fn execute(&self) -> Result<(), TokenError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = RefreshTokenRepository::new(&connection);
+        match repository.find_family(&hash_secret(&self.refresh_token)).await {
+            Ok(family_id) => Ok(repository.revoke_family(&family_id).await?),
+            Err(DatabaseError::NotFound) => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::{RngExt, rngs::StdRng};
+use thiserror::Error;
+use totp_rs::{Algorithm, TOTP};
+
+use crate::app::db::DatabaseError;
+use crate::app::models::access_token::hash_secret;
+
+mod confirmation;
+pub use confirmation::TotpConfirmation;
+
+mod deactivation;
+pub use deactivation::TotpDeactivation;
+
+mod enrolment;
+pub use enrolment::{PendingEnrolment, TotpEnrolment};
+
+mod recovery;
+pub use recovery::{RecoveryCodeRegeneration, RecoveryCodes};
+
+mod verification;
+pub use verification::{TotpVerification, second_factor_required};
+
+pub const ISSUER: &str = "Actixexp";
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// Failed codes tolerated for one pending sign-in before it is discarded.
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Failed codes tolerated for one identity across all of its sessions
+/// before its second factor is locked for `LOCKOUT_SECONDS`.
+const MAX_IDENTITY_ATTEMPTS: i32 = 10;
+const LOCKOUT_SECONDS: f64 = 900.0;
+
+const DIGITS: usize = 6;
+const STEP_SECONDS: u64 = 30;
+const SECRET_LENGTH: usize = 20;
+
+#[derive(Debug, Error)]
+pub enum TotpError {
+    #[error("TOTP is already enabled")]
+    AlreadyEnabled,
+
+    #[error("TOTP is not enrolled")]
+    NotEnrolled,
+
+    #[error("Code is invalid")]
+    InvalidCode,
+
+    #[error("No sign-in is waiting for a second factor")]
+    NotPending,
+
+    #[error("Too many invalid codes")]
+    TooManyAttempts,
+
+    #[error("Failed to build TOTP parameters")]
+    ProvisioningFailed,
+
+    #[error("Failed to save state to session")]
+    StateSavingFailed,
+
+    #[error("Failed to load state from session")]
+    StateLoadingFailed,
+
+    #[error("Database error: {source}")]
+    DatabaseError {
+        #[from]
+        source: DatabaseError,
+    },
+}
+
+fn build_totp(secret: Vec<u8>, account_name: &str) -> Result<TOTP, TotpError> {
+    let account_name = account_name.replace(':', "");
+    TOTP::new(Algorithm::SHA1, DIGITS, 0, STEP_SECONDS, secret, Some(ISSUER.to_owned()), account_name)
+        .or(Err(TotpError::ProvisioningFailed))
+}
+
+/// Finds the time step `code` belongs to, allowing one step of clock drift
+/// either way.
+fn matching_step(totp: &TOTP, code: &str) -> Option<i64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    let current = now / STEP_SECONDS;
+    (current - 1..=current + 1)
+        .find(|step| totp.check(code, step * STEP_SECONDS))
+        .map(|step| step as i64)
+}
+
+fn generate_secret() -> Vec<u8> {
+    let mut rng: StdRng = rand::make_rng();
+    let mut rs: [u8; SECRET_LENGTH] = [0; SECRET_LENGTH];
+    rng.fill(&mut rs);
+    rs.to_vec()
+}
+
+fn generate_recovery_codes() -> Vec<String> {
+    let mut rng: StdRng = rand::make_rng();
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut rs: [u8; 5] = [0; 5];
+            rng.fill(&mut rs);
+            let hex: String = rs.iter().map(|b| format!("{:02x}", b)).collect();
+            format!("{}-{}", &hex[..5], &hex[5..])
+        })
+        .collect()
+}
+
+/// Recovery codes are matched case-insensitively and without separators.
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hash_secret(&normalized)
+}
+
+fn is_totp_code(code: &str) -> bool {
+    code.len() == DIGITS && code.chars().all(|c| c.is_ascii_digit())
+}
@@ -0,0 +1,42 @@
+use crate::app::context::Context;
+use crate::app::db::DatabaseError;
+use crate::app::db::totp_repository::TotpRepository;
+use super::{RecoveryCodes, TotpError, build_totp, generate_recovery_codes, hash_recovery_code, matching_step};
+
+pub struct TotpConfirmation<'a> {
+    context: &'a Context,
+    identity_id: String,
+    code: String,
+}
+
+impl<'a> TotpConfirmation<'a> {
+    pub fn new(context: &'a Context, identity_id: &str, code: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+            code: code.trim().to_owned(),
+        }
+    }
+
+    /// Enables TOTP once the app proves it has the secret, handing out the
+    /// initial set of recovery codes.
+    pub async fn execute(&self) -> Result<RecoveryCodes, TotpError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = TotpRepository::new(&connection);
+        let secret = match repository.find(&self.identity_id).await {
+            Ok(secret) if secret.confirmed => return Err(TotpError::AlreadyEnabled),
+            Ok(secret) => secret,
+            Err(DatabaseError::NotFound) => return Err(TotpError::NotEnrolled),
+            Err(e) => return Err(e.into()),
+        };
+        let totp = build_totp(secret.secret, &self.identity_id)?;
+        let step = matching_step(&totp, &self.code).ok_or(TotpError::InvalidCode)?;
+
+        let recovery_codes = generate_recovery_codes();
+        let code_hashes: Vec<String> = recovery_codes.iter().map(|code| hash_recovery_code(code)).collect();
+        if !repository.confirm(&self.identity_id, step, &code_hashes).await? {
+            return Err(TotpError::AlreadyEnabled)
+        }
+        Ok(RecoveryCodes { recovery_codes })
+    }
+}
@@ -0,0 +1,40 @@
+use crate::app::context::Context;
+use crate::app::db::DatabaseError;
+use crate::app::db::totp_repository::TotpRepository;
+use super::{TotpError, TotpVerification};
+
+/// Turns TOTP off. Once enabled, that takes a current code or a recovery
+/// code, so a hijacked session cannot strip the second factor; a pending
+/// enrolment can be dropped without one.
+pub struct TotpDeactivation<'a> {
+    context: &'a Context,
+    identity_id: String,
+    code: String,
+}
+
+impl<'a> TotpDeactivation<'a> {
+    pub fn new(context: &'a Context, identity_id: &str, code: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+            code: code.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<(), TotpError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = TotpRepository::new(&connection);
+        match repository.find(&self.identity_id).await {
+            Ok(secret) if secret.confirmed => {
+                TotpVerification::new(self.context, &self.identity_id, &self.code).execute().await?;
+            }
+            Ok(_) => {},
+            Err(DatabaseError::NotFound) => return Err(TotpError::NotEnrolled),
+            Err(e) => return Err(e.into()),
+        }
+        match repository.delete(&self.identity_id).await {
+            Err(DatabaseError::NotFound) => Err(TotpError::NotEnrolled),
+            result => Ok(result?),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use serde_derive::Serialize;
+
+use crate::app::context::Context;
+use crate::app::db::totp_repository::TotpRepository;
+use crate::app::models::Identity;
+use super::{TotpError, build_totp, generate_secret};
+
+/// A secret waiting to be confirmed with a first code. It is only shown
+/// here, for the authenticator app to scan or type in.
+#[derive(Serialize)]
+pub struct PendingEnrolment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+pub struct TotpEnrolment<'a> {
+    context: &'a Context,
+    identity: &'a Identity,
+}
+
+impl<'a> TotpEnrolment<'a> {
+    pub fn new(context: &'a Context, identity: &'a Identity) -> Self {
+        Self {
+            context,
+            identity,
+        }
+    }
+
+    pub async fn execute(&self) -> Result<PendingEnrolment, TotpError> {
+        let secret = generate_secret();
+        let account_name = self.identity.login.as_deref().unwrap_or(&self.identity.id);
+        let totp = build_totp(secret.clone(), account_name)?;
+
+        let connection = self.context.db.establish_connection().await?;
+        let repository = TotpRepository::new(&connection);
+        if !repository.begin(&self.identity.id, &secret).await? {
+            return Err(TotpError::AlreadyEnabled)
+        }
+
+        let enrolment = PendingEnrolment {
+            secret: totp.get_secret_base32(),
+            otpauth_uri: totp.get_url(),
+        };
+        Ok(enrolment)
+    }
+}
@@ -0,0 +1,44 @@
+use serde_derive::Serialize;
+
+use crate::app::context::Context;
+use crate::app::db::totp_repository::TotpRepository;
+use super::{TotpError, TotpVerification, generate_recovery_codes, hash_recovery_code};
+
+/// Single-use codes accepted in place of a TOTP code. Only their hashes are
+/// kept, so they are shown just once.
+#[derive(Serialize)]
+pub struct RecoveryCodes {
+    pub recovery_codes: Vec<String>,
+}
+
+/// Replaces the recovery codes. Like turning TOTP off, this takes a current
+/// code or a recovery code, so a hijacked session cannot mint its own.
+pub struct RecoveryCodeRegeneration<'a> {
+    context: &'a Context,
+    identity_id: String,
+    code: String,
+}
+
+impl<'a> RecoveryCodeRegeneration<'a> {
+    pub fn new(context: &'a Context, identity_id: &str, code: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+            code: code.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<RecoveryCodes, TotpError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = TotpRepository::new(&connection);
+        if !repository.is_enabled(&self.identity_id).await? {
+            return Err(TotpError::NotEnrolled)
+        }
+        TotpVerification::new(self.context, &self.identity_id, &self.code).execute().await?;
+
+        let recovery_codes = generate_recovery_codes();
+        let code_hashes: Vec<String> = recovery_codes.iter().map(|code| hash_recovery_code(code)).collect();
+        repository.replace_recovery_codes(&self.identity_id, &code_hashes).await?;
+        Ok(RecoveryCodes { recovery_codes })
+    }
+}
@@ -0,0 +1,63 @@
+use crate::app::context::Context;
+use crate::app::db::DatabaseError;
+use crate::app::db::totp_repository::TotpRepository;
+use super::{LOCKOUT_SECONDS, MAX_IDENTITY_ATTEMPTS, TotpError, build_totp, hash_recovery_code, is_totp_code, matching_step};
+
+pub async fn second_factor_required(context: &Context, identity_id: &str) -> Result<bool, TotpError> {
+    let connection = context.db.establish_connection().await?;
+    let repository = TotpRepository::new(&connection);
+    Ok(repository.is_enabled(identity_id).await?)
+}
+
+/// Checks the second factor: either a current TOTP code or one of the
+/// recovery codes, which is consumed. Failures count against the identity,
+/// so starting new sessions does not buy more guesses.
+pub struct TotpVerification<'a> {
+    context: &'a Context,
+    identity_id: String,
+    code: String,
+}
+
+impl<'a> TotpVerification<'a> {
+    pub fn new(context: &'a Context, identity_id: &str, code: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+            code: code.trim().to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<(), TotpError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = TotpRepository::new(&connection);
+        let secret = match repository.find(&self.identity_id).await {
+            Ok(secret) if secret.confirmed => secret,
+            Ok(_) | Err(DatabaseError::NotFound) => return Err(TotpError::NotEnrolled),
+            Err(e) => return Err(e.into()),
+        };
+        if secret.locked {
+            return Err(TotpError::TooManyAttempts)
+        }
+
+        let accepted = match is_totp_code(&self.code) {
+            true => {
+                let totp = build_totp(secret.secret, &self.identity_id)?;
+                match matching_step(&totp, &self.code) {
+                    Some(step) => repository.record_step(&self.identity_id, step).await?,
+                    None => false,
+                }
+            }
+            false => repository.use_recovery_code(&self.identity_id, &hash_recovery_code(&self.code)).await?,
+        };
+        match accepted {
+            true => {
+                repository.reset_failures(&self.identity_id).await?;
+                Ok(())
+            }
+            false => {
+                repository.record_failure(&self.identity_id, MAX_IDENTITY_ATTEMPTS, LOCKOUT_SECONDS).await?;
+                Err(TotpError::InvalidCode)
+            }
+        }
+    }
+}
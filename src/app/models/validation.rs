@@ -0,0 +1,336 @@
+use std::str::FromStr;
+
+use serde_derive::Serialize;
+use thiserror::Error;
+
+pub trait Validate {
+    type Output;
+
+    fn validate(&self) -> Result<Self::Output, ValidationErrors>;
+}
+
+#[derive(Debug, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: &'static str,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<Vec<&'static str>>,
+}
+
+impl FieldError {
+    fn new(field: &str, code: &'static str, message: String) -> Self {
+        Self {
+            field: field.to_owned(),
+            code,
+            message,
+            allowed: None,
+        }
+    }
+}
+
+#[derive(Debug, Error, Serialize)]
+#[error("Validation failed")]
+pub struct ValidationErrors {
+    pub errors: Vec<FieldError>,
+}
+
+impl ValidationErrors {
+    pub fn single(field: &str, code: &'static str, message: String) -> Self {
+        Self {
+            errors: vec![FieldError::new(field, code, message)],
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TextRules {
+    required: bool,
+    max_length: Option<usize>,
+}
+
+impl TextRules {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub fn max_length(mut self, max_length: usize) -> Self {
+        self.max_length = Some(max_length);
+        self
+    }
+}
+
+pub struct ChoiceRules {
+    required: bool,
+    allowed: Vec<&'static str>,
+}
+
+impl ChoiceRules {
+    pub fn new(allowed: Vec<&'static str>) -> Self {
+        Self {
+            required: false,
+            allowed,
+        }
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+}
+
+pub struct IntegerRules {
+    min: i64,
+    max: i64,
+}
+
+impl IntegerRules {
+    pub fn new(min: i64, max: i64) -> Self {
+        Self {
+            min,
+            max,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&mut self, field: &str, value: Option<&str>, rules: &TextRules) -> Option<String> {
+        let value = match value {
+            Some(value) => value.trim(),
+            None => {
+                if rules.required {
+                    self.required(field);
+                }
+                return None
+            }
+        };
+
+        if value.is_empty() {
+            let message = format!("{} must not be blank", field);
+            self.errors.push(FieldError::new(field, "blank", message));
+            return None
+        }
+
+        if let Some(max_length) = rules.max_length {
+            if value.chars().count() > max_length {
+                let message = format!("{} must be at most {} characters", field, max_length);
+                self.errors.push(FieldError::new(field, "too_long", message));
+                return None
+            }
+        }
+
+        Some(value.to_owned())
+    }
+
+    pub fn choice<T: FromStr>(&mut self, field: &str, value: Option<&str>, rules: &ChoiceRules) -> Option<T> {
+        let value = match value {
+            Some(value) => value.trim(),
+            None => {
+                if rules.required {
+                    self.required(field);
+                }
+                return None
+            }
+        };
+
+        match value.parse::<T>() {
+            Ok(choice) if rules.allowed.contains(&value) => Some(choice),
+            _ => {
+                let message = format!("{} must be one of: {}", field, rules.allowed.join(", "));
+                let mut error = FieldError::new(field, "invalid_choice", message);
+                error.allowed = Some(rules.allowed.clone());
+                self.errors.push(error);
+                None
+            }
+        }
+    }
+
+    pub fn integer(&mut self, field: &str, value: Option<&str>, rules: &IntegerRules) -> Option<i64> {
+        let value = value?.trim();
+        let number = match value.parse::<i64>() {
+            Ok(number) => number,
+            Err(_) => {
+                let message = format!("{} must be an integer", field);
+                self.errors.push(FieldError::new(field, "not_integer", message));
+                return None
+            }
+        };
+
+        if number < rules.min || number > rules.max {
+            let message = format!("{} must be between {} and {}", field, rules.min, rules.max);
+            self.errors.push(FieldError::new(field, "out_of_range", message));
+            return None
+        }
+
+        Some(number)
+    }
+
+    pub fn parsed<T: FromStr>(&mut self, field: &str, value: Option<&str>) -> Option<T> {
+        let value = value?;
+        match value.parse::<T>() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                let message = format!("{} is malformed", field);
+                self.errors.push(FieldError::new(field, "malformed", message));
+                None
+            }
+        }
+    }
+
+    /// Records a failed check the rules above do not cover.
+    pub fn invalid(&mut self, field: &str, code: &'static str, message: String) {
+        self.errors.push(FieldError::new(field, code, message));
+    }
+
+    pub fn present<T>(&mut self, field: &str, value: Option<T>) -> Option<T> {
+        if value.is_none() {
+            self.required(field);
+        }
+        value
+    }
+
+    pub fn finish<T>(self, value: Option<T>) -> Result<T, ValidationErrors> {
+        match value {
+            Some(value) if self.errors.is_empty() => Ok(value),
+            _ => Err(ValidationErrors { errors: self.errors }),
+        }
+    }
+
+    fn required(&mut self, field: &str) {
+        let message = format!("{} is required", field);
+        self.errors.push(FieldError::new(field, "required", message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Colour {
+        Red,
+        Blue,
+    }
+
+    impl FromStr for Colour {
+        type Err = ();
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "red" => Ok(Self::Red),
+                "blue" => Ok(Self::Blue),
+                _ => Err(()),
+            }
+        }
+    }
+
+    fn codes(errors: &ValidationErrors) -> Vec<(&str, &str)> {
+        errors.errors.iter().map(|error| (error.field.as_str(), error.code)).collect()
+    }
+
+    #[test]
+    fn trims_text_and_accepts_it() {
+        let mut validator = Validator::new();
+        let rules = TextRules::new().required().max_length(5);
+        let value = validator.text("name", Some("  Mash "), &rules);
+        assert_eq!(validator.finish(value).unwrap(), "Mash");
+    }
+
+    #[test]
+    fn reports_missing_blank_and_long_text() {
+        let mut validator = Validator::new();
+        let rules = TextRules::new().required().max_length(3);
+        validator.text("missing", None, &rules);
+        validator.text("blank", Some("   "), &rules);
+        validator.text("long", Some("abcd"), &rules);
+        let errors = validator.finish(Some(())).unwrap_err();
+        assert_eq!(codes(&errors), vec![("missing", "required"), ("blank", "blank"), ("long", "too_long")]);
+    }
+
+    #[test]
+    fn optional_text_may_be_absent() {
+        let mut validator = Validator::new();
+        let value = validator.text("name", None, &TextRules::new());
+        assert_eq!(value, None);
+        assert!(validator.finish(Some(())).is_ok());
+    }
+
+    #[test]
+    fn parses_choices_and_lists_allowed_values_on_failure() {
+        let mut validator = Validator::new();
+        let rules = ChoiceRules::new(vec!["red", "blue"]).required();
+        assert_eq!(validator.choice::<Colour>("colour", Some(" blue "), &rules), Some(Colour::Blue));
+        assert_eq!(validator.choice::<Colour>("colour", Some("green"), &rules), None);
+        let errors = validator.finish(Some(())).unwrap_err();
+        assert_eq!(codes(&errors), vec![("colour", "invalid_choice")]);
+        assert_eq!(errors.errors[0].allowed, Some(vec!["red", "blue"]));
+    }
+
+    #[test]
+    fn rejects_choices_outside_the_allowed_list() {
+        let mut validator = Validator::new();
+        let rules = ChoiceRules::new(vec!["red"]);
+        assert_eq!(validator.choice::<Colour>("colour", Some("blue"), &rules), None);
+        assert!(validator.finish(Some(())).is_err());
+    }
+
+    #[test]
+    fn checks_integers_and_their_range() {
+        let mut validator = Validator::new();
+        let rules = IntegerRules::new(1, 10);
+        assert_eq!(validator.integer("limit", Some("10"), &rules), Some(10));
+        assert_eq!(validator.integer("limit", None, &rules), None);
+        validator.integer("limit", Some("ten"), &rules);
+        validator.integer("limit", Some("11"), &rules);
+        let errors = validator.finish(Some(())).unwrap_err();
+        assert_eq!(codes(&errors), vec![("limit", "not_integer"), ("limit", "out_of_range")]);
+    }
+
+    #[test]
+    fn reports_malformed_and_required_values() {
+        let mut validator = Validator::new();
+        assert_eq!(validator.parsed::<Colour>("colour", Some("green")), None);
+        assert_eq!(validator.present::<i32>("count", None), None);
+        let errors = validator.finish(Some(())).unwrap_err();
+        assert_eq!(codes(&errors), vec![("colour", "malformed"), ("count", "required")]);
+    }
+
+    #[test]
+    fn serializes_errors_as_the_response_body() {
+        let mut validator = Validator::new();
+        validator.choice::<Colour>("colour", Some("green"), &ChoiceRules::new(vec!["red"]));
+        validator.text("name", Some(""), &TextRules::new());
+        let errors = validator.finish(Some(())).unwrap_err();
+        assert_eq!(serde_json::to_value(&errors).unwrap(), json!({
+            "errors": [
+                {
+                    "field": "colour",
+                    "code": "invalid_choice",
+                    "message": "colour must be one of: red",
+                    "allowed": ["red"],
+                },
+                {
+                    "field": "name",
+                    "code": "blank",
+                    "message": "name must not be blank",
+                },
+            ],
+        }));
+    }
+}
@@ -0,0 +1,125 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use rand::{RngExt, rngs::StdRng};
+use thiserror::Error;
+
+use crate::app::config::ApplicationConfig;
+use crate::app::db::DatabaseError;
+
+pub use crate::app::db::webauthn_credential_repository::Passkey;
+
+mod authenticator_data;
+mod client_data;
+mod public_key;
+
+mod authentication;
+pub use authentication::{AuthenticationOptions, PasskeyAssertion, PasskeyAuthentication};
+
+mod listing;
+pub use listing::PasskeyListing;
+
+mod registration;
+pub use registration::{PasskeyAttestation, PasskeyRegistration, RegistrationOptions};
+
+mod removal;
+pub use removal::PasskeyRemoval;
+
+pub const NAME_MAX_LENGTH: usize = 100;
+pub const DEFAULT_NAME: &str = "Passkey";
+
+/// How long the browser may take to complete a ceremony, in milliseconds.
+pub const CEREMONY_TIMEOUT: u32 = 300_000;
+
+/// COSE algorithms offered to authenticators, in order of preference.
+pub const SUPPORTED_ALGORITHMS: [i32; 3] = [public_key::ES256, public_key::EDDSA, public_key::RS256];
+
+#[derive(Debug, Error)]
+pub enum WebAuthnError {
+    #[error("No saved challenge found")]
+    ChallengeMissing,
+
+    #[error("Client data does not match the saved challenge")]
+    ChallengeNotMatch,
+
+    #[error("Client data is malformed")]
+    InvalidClientData,
+
+    #[error("Client data is for another ceremony")]
+    CeremonyNotMatch,
+
+    #[error("Origin is not allowed")]
+    OriginNotAllowed,
+
+    #[error("Attestation object is malformed")]
+    InvalidAttestation,
+
+    #[error("Authenticator data is malformed")]
+    InvalidAuthenticatorData,
+
+    #[error("Relying party ID does not match")]
+    RpIdNotMatch,
+
+    #[error("User presence was not asserted")]
+    UserNotPresent,
+
+    #[error("Public key algorithm is not supported")]
+    UnsupportedAlgorithm,
+
+    #[error("Passkey is already registered")]
+    CredentialAlreadyRegistered,
+
+    #[error("Passkey is not registered")]
+    CredentialNotFound,
+
+    #[error("Assertion signature is invalid")]
+    InvalidSignature,
+
+    #[error("User handle does not match the passkey")]
+    UserHandleNotMatch,
+
+    #[error("Signature counter did not increase")]
+    CounterNotIncreased,
+
+    #[error("Identity is deactivated")]
+    IdentityDeactivated,
+
+    #[error("Failed to save challenge to session")]
+    StateSavingFailed,
+
+    #[error("Failed to load challenge from session")]
+    StateLoadingFailed,
+
+    #[error("Database error: {source}")]
+    DatabaseError {
+        #[from]
+        source: DatabaseError,
+    },
+}
+
+/// The relying party this server acts as, resolved from `[webauthn]` with
+/// fallbacks derived from the frontend base URI.
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+    pub origins: Vec<String>,
+}
+
+impl RelyingParty {
+    pub fn from_config(config: &ApplicationConfig) -> Self {
+        Self {
+            id: config.webauthn.rp_id(&config.frontend),
+            name: config.webauthn.rp_name(),
+            origins: config.webauthn.origins(&config.frontend),
+        }
+    }
+}
+
+pub fn generate_challenge() -> String {
+    let mut rng: StdRng = rand::make_rng();
+    let mut rs: [u8; 32] = [0; 32];
+    rng.fill(&mut rs);
+    URL_SAFE_NO_PAD.encode(rs)
+}
+
+fn decode(value: &str, error: WebAuthnError) -> Result<Vec<u8>, WebAuthnError> {
+    URL_SAFE_NO_PAD.decode(value).or(Err(error))
+}
@@ -0,0 +1,118 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::app::context::Context;
+use crate::app::db::DatabaseError;
+use crate::app::db::identity_repository::IdentityRepository;
+use crate::app::db::webauthn_credential_repository::WebAuthnCredentialRepository;
+use crate::app::models::Identity;
+use super::authenticator_data::AuthenticatorData;
+use super::public_key::PublicKey;
+use super::{CEREMONY_TIMEOUT, RelyingParty, WebAuthnError, client_data, decode};
+
+/// `PublicKeyCredentialRequestOptions` handed to `navigator.credentials.get()`.
+/// No credentials are listed, so the authenticator offers its discoverable
+/// passkeys for this RP.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticationOptions {
+    challenge: String,
+    rp_id: String,
+    timeout: u32,
+    user_verification: &'static str,
+}
+
+impl AuthenticationOptions {
+    pub fn new(context: &Context, challenge: &str) -> Self {
+        Self {
+            challenge: challenge.to_owned(),
+            rp_id: RelyingParty::from_config(&context.config).id,
+            timeout: CEREMONY_TIMEOUT,
+            user_verification: "preferred",
+        }
+    }
+}
+
+/// The `PublicKeyCredential` returned by `navigator.credentials.get()`,
+/// with binary fields encoded as base64url.
+#[derive(Deserialize)]
+pub struct PasskeyAssertion {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+pub struct PasskeyAuthentication<'a> {
+    context: &'a Context,
+    challenge: Option<String>,
+    assertion: PasskeyAssertion,
+}
+
+impl<'a> PasskeyAuthentication<'a> {
+    pub fn new(context: &'a Context, challenge: Option<String>, assertion: PasskeyAssertion) -> Self {
+        Self {
+            context,
+            challenge,
+            assertion,
+        }
+    }
+
+    pub async fn execute(self) -> Result<Identity, WebAuthnError> {
+        let challenge = self.challenge.ok_or(WebAuthnError::ChallengeMissing)?;
+        let rp = RelyingParty::from_config(&self.context.config);
+        let response = &self.assertion.response;
+
+        let client_data_json = decode(&response.client_data_json, WebAuthnError::InvalidClientData)?;
+        client_data::verify(&client_data_json, client_data::GET, &challenge, &rp)?;
+        let raw_auth_data = decode(&response.authenticator_data, WebAuthnError::InvalidAuthenticatorData)?;
+        let auth_data = AuthenticatorData::parse(&raw_auth_data, &rp)?;
+        let signature = decode(&response.signature, WebAuthnError::InvalidSignature)?;
+
+        let connection = self.context.db.establish_connection().await?;
+        let repository = WebAuthnCredentialRepository::new(&connection);
+        let passkey = match repository.find_by_credential_id(&self.assertion.id).await {
+            Ok(passkey) => passkey,
+            Err(DatabaseError::NotFound) => return Err(WebAuthnError::CredentialNotFound),
+            Err(e) => return Err(e.into()),
+        };
+        if let Some(user_handle) = &response.user_handle {
+            if *user_handle != URL_SAFE_NO_PAD.encode(passkey.identity_id.as_bytes()) {
+                return Err(WebAuthnError::UserHandleNotMatch)
+            }
+        }
+
+        let mut message = raw_auth_data.clone();
+        message.extend(Sha256::digest(&client_data_json));
+        PublicKey::from_cose(&passkey.public_key)?.verify(&message, &signature)?;
+
+        // Authenticators without a counter always report zero; any other
+        // value must move forward or the credential may have been cloned.
+        let sign_count = auth_data.sign_count as i64;
+        if (sign_count != 0 || passkey.sign_count != 0) && sign_count <= passkey.sign_count {
+            return Err(WebAuthnError::CounterNotIncreased)
+        }
+        match repository.record_use(&passkey.id, passkey.sign_count, sign_count).await {
+            Ok(()) => (),
+            Err(DatabaseError::NotFound) => return Err(WebAuthnError::CounterNotIncreased),
+            Err(e) => return Err(e.into()),
+        }
+
+        let identities = IdentityRepository::new(&connection);
+        let identity = identities.find_by_id(&passkey.identity_id).await?;
+        if !identity.alive {
+            return Err(WebAuthnError::IdentityDeactivated)
+        }
+        Ok(identity)
+    }
+}
@@ -0,0 +1,174 @@
+use ciborium::Value;
+use sha2::{Digest, Sha256};
+
+use super::{RelyingParty, WebAuthnError};
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+/// Fixed-size prefix: RP ID hash, flags and signature counter.
+const HEADER_LENGTH: usize = 37;
+const AAGUID_LENGTH: usize = 16;
+
+pub struct AuthenticatorData {
+    pub sign_count: u32,
+    pub attested_credential: Option<AttestedCredential>,
+}
+
+pub struct AttestedCredential {
+    pub credential_id: Vec<u8>,
+    /// The credential public key as the authenticator encoded it (COSE).
+    pub public_key: Vec<u8>,
+}
+
+impl AuthenticatorData {
+    /// Parses authenticator data, requiring it to be scoped to our RP ID and
+    /// to assert user presence.
+    pub fn parse(data: &[u8], rp: &RelyingParty) -> Result<Self, WebAuthnError> {
+        if data.len() < HEADER_LENGTH {
+            return Err(WebAuthnError::InvalidAuthenticatorData)
+        }
+        if data[..32] != Sha256::digest(rp.id.as_bytes())[..] {
+            return Err(WebAuthnError::RpIdNotMatch)
+        }
+        let flags = data[32];
+        if flags & FLAG_USER_PRESENT == 0 {
+            return Err(WebAuthnError::UserNotPresent)
+        }
+        let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+        let attested_credential = match flags & FLAG_ATTESTED_CREDENTIAL_DATA {
+            0 => None,
+            _ => Some(Self::parse_attested_credential(&data[HEADER_LENGTH..])?),
+        };
+        let authenticator_data = Self {
+            sign_count,
+            attested_credential,
+        };
+        Ok(authenticator_data)
+    }
+
+    fn parse_attested_credential(data: &[u8]) -> Result<AttestedCredential, WebAuthnError> {
+        let data = data.get(AAGUID_LENGTH..).ok_or(WebAuthnError::InvalidAuthenticatorData)?;
+        let length_bytes = data.get(..2).ok_or(WebAuthnError::InvalidAuthenticatorData)?;
+        let length = u16::from_be_bytes([length_bytes[0], length_bytes[1]]) as usize;
+        let credential_id = data.get(2..2 + length).ok_or(WebAuthnError::InvalidAuthenticatorData)?;
+
+        // The key is followed by optional extension data, so only the bytes
+        // the CBOR decoder consumed belong to it.
+        let key_data = &data[2 + length..];
+        let mut reader = key_data;
+        ciborium::from_reader::<Value, _>(&mut reader).or(Err(WebAuthnError::InvalidAuthenticatorData))?;
+        let key_length = key_data.len() - reader.len();
+
+        let credential = AttestedCredential {
+            credential_id: credential_id.to_vec(),
+            public_key: key_data[..key_length].to_vec(),
+        };
+        Ok(credential)
+    }
+}
+
+/// Extracts the authenticator data from an attestation object. The
+/// attestation statement is not checked, since registration asks for
+/// `"none"` conveyance.
+pub fn parse_attestation_object(attestation_object: &[u8]) -> Result<Vec<u8>, WebAuthnError> {
+    let value: Value = ciborium::from_reader(attestation_object).or(Err(WebAuthnError::InvalidAttestation))?;
+    let entries = value.into_map().or(Err(WebAuthnError::InvalidAttestation))?;
+    entries.into_iter()
+        .find(|(key, _)| key.as_text() == Some("authData"))
+        .and_then(|(_, value)| value.into_bytes().ok())
+        .ok_or(WebAuthnError::InvalidAttestation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relying_party() -> RelyingParty {
+        RelyingParty {
+            id: "localhost".to_owned(),
+            name: "Actixexp".to_owned(),
+            origins: vec!["http://localhost:3000".to_owned()],
+        }
+    }
+
+    fn header(rp_id: &str, flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend(sign_count.to_be_bytes());
+        data
+    }
+
+    fn cose_key() -> Vec<u8> {
+        let key = Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer(2.into())),
+            (Value::Integer(3.into()), Value::Integer((-7).into())),
+        ]);
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&key, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn parses_an_assertion_header() {
+        let data = header("localhost", FLAG_USER_PRESENT, 7);
+        let parsed = AuthenticatorData::parse(&data, &relying_party()).unwrap();
+        assert_eq!(parsed.sign_count, 7);
+        assert!(parsed.attested_credential.is_none());
+    }
+
+    #[test]
+    fn parses_attested_credential_and_skips_extensions() {
+        let mut data = header("localhost", FLAG_USER_PRESENT | FLAG_ATTESTED_CREDENTIAL_DATA, 0);
+        data.extend([0; AAGUID_LENGTH]);
+        data.extend(3u16.to_be_bytes());
+        data.extend([1, 2, 3]);
+        data.extend(cose_key());
+        let mut extensions = Vec::new();
+        ciborium::into_writer(&Value::Map(vec![(Value::Text("credProtect".to_owned()), Value::Integer(1.into()))]), &mut extensions).unwrap();
+        data.extend(extensions);
+
+        let parsed = AuthenticatorData::parse(&data, &relying_party()).unwrap();
+        let credential = parsed.attested_credential.unwrap();
+        assert_eq!(credential.credential_id, vec![1, 2, 3]);
+        assert_eq!(credential.public_key, cose_key());
+    }
+
+    #[test]
+    fn rejects_other_relying_parties() {
+        let data = header("example.com", FLAG_USER_PRESENT, 0);
+        assert!(matches!(AuthenticatorData::parse(&data, &relying_party()), Err(WebAuthnError::RpIdNotMatch)));
+    }
+
+    #[test]
+    fn requires_user_presence() {
+        let data = header("localhost", 0, 0);
+        assert!(matches!(AuthenticatorData::parse(&data, &relying_party()), Err(WebAuthnError::UserNotPresent)));
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let data = header("localhost", FLAG_USER_PRESENT, 0);
+        assert!(matches!(AuthenticatorData::parse(&data[..36], &relying_party()), Err(WebAuthnError::InvalidAuthenticatorData)));
+
+        let mut data = header("localhost", FLAG_USER_PRESENT | FLAG_ATTESTED_CREDENTIAL_DATA, 0);
+        data.extend([0; AAGUID_LENGTH]);
+        data.extend(16u16.to_be_bytes());
+        data.extend([1, 2, 3]);
+        assert!(matches!(AuthenticatorData::parse(&data, &relying_party()), Err(WebAuthnError::InvalidAuthenticatorData)));
+    }
+
+    #[test]
+    fn extracts_auth_data_from_attestation_object() {
+        let object = Value::Map(vec![
+            (Value::Text("fmt".to_owned()), Value::Text("none".to_owned())),
+            (Value::Text("attStmt".to_owned()), Value::Map(vec![])),
+            (Value::Text("authData".to_owned()), Value::Bytes(vec![9, 9])),
+        ]);
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&object, &mut bytes).unwrap();
+        assert_eq!(parse_attestation_object(&bytes).unwrap(), vec![9, 9]);
+        assert!(matches!(parse_attestation_object(&[0xff]), Err(WebAuthnError::InvalidAttestation)));
+    }
+}
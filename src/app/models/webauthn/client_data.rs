@@ -0,0 +1,34 @@
+use serde_derive::Deserialize;
+
+use super::{RelyingParty, WebAuthnError};
+
+pub const CREATE: &str = "webauthn.create";
+pub const GET: &str = "webauthn.get";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectedClientData {
+    #[serde(rename = "type")]
+    ceremony: String,
+    challenge: String,
+    origin: String,
+    #[serde(default)]
+    cross_origin: bool,
+}
+
+/// Checks the `clientDataJSON` the browser signed against the ceremony
+/// the session started.
+pub fn verify(client_data_json: &[u8], ceremony: &str, challenge: &str, rp: &RelyingParty) -> Result<(), WebAuthnError> {
+    let client_data: CollectedClientData = serde_json::from_slice(client_data_json)
+        .or(Err(WebAuthnError::InvalidClientData))?;
+    if client_data.ceremony != ceremony {
+        return Err(WebAuthnError::CeremonyNotMatch)
+    }
+    if client_data.challenge != challenge {
+        return Err(WebAuthnError::ChallengeNotMatch)
+    }
+    if client_data.cross_origin || !rp.origins.contains(&client_data.origin) {
+        return Err(WebAuthnError::OriginNotAllowed)
+    }
+    Ok(())
+}
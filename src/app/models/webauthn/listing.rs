@@ -0,0 +1,25 @@
+use crate::app::context::Context;
+use crate::app::db::webauthn_credential_repository::WebAuthnCredentialRepository;
+use crate::app::models::DomainError;
+use super::Passkey;
+
+pub struct PasskeyListing<'a> {
+    context: &'a Context,
+    identity_id: String,
+}
+
+impl<'a> PasskeyListing<'a> {
+    pub fn new(context: &'a Context, identity_id: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<Vec<Passkey>, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = WebAuthnCredentialRepository::new(&connection);
+        let passkeys = repository.list(&self.identity_id).await?;
+        Ok(passkeys)
+    }
+}
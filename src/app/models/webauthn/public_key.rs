@@ -0,0 +1,189 @@
+use ciborium::Value;
+use ring::signature::{self, RsaPublicKeyComponents, UnparsedPublicKey};
+
+use super::WebAuthnError;
+
+pub const ES256: i32 = -7;
+pub const EDDSA: i32 = -8;
+pub const RS256: i32 = -257;
+
+const KEY_TYPE: i128 = 1;
+const ALGORITHM: i128 = 3;
+const EC2_CURVE: i128 = -1;
+const EC2_X: i128 = -2;
+const EC2_Y: i128 = -3;
+const OKP_CURVE: i128 = -1;
+const OKP_X: i128 = -2;
+const RSA_N: i128 = -1;
+const RSA_E: i128 = -2;
+
+const KEY_TYPE_OKP: i128 = 1;
+const KEY_TYPE_EC2: i128 = 2;
+const KEY_TYPE_RSA: i128 = 3;
+const CURVE_P256: i128 = 1;
+const CURVE_ED25519: i128 = 6;
+
+/// A credential public key decoded from its COSE encoding.
+pub enum PublicKey {
+    Es256 { point: Vec<u8> },
+    EdDsa { x: Vec<u8> },
+    Rs256 { n: Vec<u8>, e: Vec<u8> },
+}
+
+impl PublicKey {
+    pub fn from_cose(cose_key: &[u8]) -> Result<Self, WebAuthnError> {
+        let value: Value = ciborium::from_reader(cose_key).or(Err(WebAuthnError::InvalidAuthenticatorData))?;
+        let entries = value.into_map().or(Err(WebAuthnError::InvalidAuthenticatorData))?;
+        let integer = |label: i128| find(&entries, label)
+            .and_then(|value| value.as_integer())
+            .map(i128::from);
+        let bytes = |label: i128| find(&entries, label)
+            .and_then(|value| value.as_bytes())
+            .cloned()
+            .ok_or(WebAuthnError::InvalidAuthenticatorData);
+
+        let algorithm = integer(ALGORITHM).ok_or(WebAuthnError::UnsupportedAlgorithm)?;
+        let key = match (integer(KEY_TYPE), algorithm) {
+            (Some(KEY_TYPE_EC2), alg) if alg == ES256 as i128 && integer(EC2_CURVE) == Some(CURVE_P256) => {
+                let mut point = vec![0x04];
+                point.extend(bytes(EC2_X)?);
+                point.extend(bytes(EC2_Y)?);
+                Self::Es256 { point }
+            }
+            (Some(KEY_TYPE_OKP), alg) if alg == EDDSA as i128 && integer(OKP_CURVE) == Some(CURVE_ED25519) => {
+                Self::EdDsa { x: bytes(OKP_X)? }
+            }
+            (Some(KEY_TYPE_RSA), alg) if alg == RS256 as i128 => {
+                Self::Rs256 { n: bytes(RSA_N)?, e: bytes(RSA_E)? }
+            }
+            _ => return Err(WebAuthnError::UnsupportedAlgorithm),
+        };
+        Ok(key)
+    }
+
+    pub fn algorithm(&self) -> i32 {
+        match self {
+            Self::Es256 { .. } => ES256,
+            Self::EdDsa { .. } => EDDSA,
+            Self::Rs256 { .. } => RS256,
+        }
+    }
+
+    pub fn verify(&self, message: &[u8], signature: &[u8]) -> Result<(), WebAuthnError> {
+        let result = match self {
+            Self::Es256 { point } => {
+                UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_ASN1, point).verify(message, signature)
+            }
+            Self::EdDsa { x } => {
+                UnparsedPublicKey::new(&signature::ED25519, x).verify(message, signature)
+            }
+            Self::Rs256 { n, e } => {
+                RsaPublicKeyComponents { n, e }.verify(&signature::RSA_PKCS1_2048_8192_SHA256, message, signature)
+            }
+        };
+        result.or(Err(WebAuthnError::InvalidSignature))
+    }
+}
+
+fn find(entries: &[(Value, Value)], label: i128) -> Option<&Value> {
+    entries.iter()
+        .find(|(key, _)| key.as_integer().map(i128::from) == Some(label))
+        .map(|(_, value)| value)
+}
+
+#[cfg(test)]
+mod tests {
+    use ring::rand::SystemRandom;
+    use ring::signature::{ECDSA_P256_SHA256_ASN1_SIGNING, EcdsaKeyPair, KeyPair};
+
+    use super::*;
+
+    fn encode(entries: Vec<(i128, Value)>) -> Vec<u8> {
+        let map = entries.into_iter()
+            .map(|(label, value)| (Value::Integer(label.try_into().unwrap()), value))
+            .collect();
+        let mut bytes = Vec::new();
+        ciborium::into_writer(&Value::Map(map), &mut bytes).unwrap();
+        bytes
+    }
+
+    fn integer(value: i128) -> Value {
+        Value::Integer(value.try_into().unwrap())
+    }
+
+    #[test]
+    fn decodes_es256_keys_and_verifies_signatures() {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        let point = key_pair.public_key().as_ref();
+        let cose_key = encode(vec![
+            (KEY_TYPE, integer(KEY_TYPE_EC2)),
+            (ALGORITHM, integer(ES256 as i128)),
+            (EC2_CURVE, integer(CURVE_P256)),
+            (EC2_X, Value::Bytes(point[1..33].to_vec())),
+            (EC2_Y, Value::Bytes(point[33..].to_vec())),
+        ]);
+
+        let key = PublicKey::from_cose(&cose_key).unwrap();
+        assert_eq!(key.algorithm(), ES256);
+        let signature = key_pair.sign(&rng, b"message").unwrap();
+        assert!(key.verify(b"message", signature.as_ref()).is_ok());
+        assert!(matches!(key.verify(b"tampered", signature.as_ref()), Err(WebAuthnError::InvalidSignature)));
+    }
+
+    #[test]
+    fn decodes_eddsa_and_rs256_keys() {
+        let eddsa = encode(vec![
+            (KEY_TYPE, integer(KEY_TYPE_OKP)),
+            (ALGORITHM, integer(EDDSA as i128)),
+            (OKP_CURVE, integer(CURVE_ED25519)),
+            (OKP_X, Value::Bytes(vec![0; 32])),
+        ]);
+        assert_eq!(PublicKey::from_cose(&eddsa).unwrap().algorithm(), EDDSA);
+
+        let rs256 = encode(vec![
+            (KEY_TYPE, integer(KEY_TYPE_RSA)),
+            (ALGORITHM, integer(RS256 as i128)),
+            (RSA_N, Value::Bytes(vec![0xc5; 256])),
+            (RSA_E, Value::Bytes(vec![1, 0, 1])),
+        ]);
+        assert_eq!(PublicKey::from_cose(&rs256).unwrap().algorithm(), RS256);
+    }
+
+    #[test]
+    fn rejects_unsupported_algorithms_and_curves() {
+        let es384 = encode(vec![
+            (KEY_TYPE, integer(KEY_TYPE_EC2)),
+            (ALGORITHM, integer(-35)),
+            (EC2_CURVE, integer(2)),
+        ]);
+        assert!(matches!(PublicKey::from_cose(&es384), Err(WebAuthnError::UnsupportedAlgorithm)));
+
+        let wrong_curve = encode(vec![
+            (KEY_TYPE, integer(KEY_TYPE_EC2)),
+            (ALGORITHM, integer(ES256 as i128)),
+            (EC2_CURVE, integer(2)),
+        ]);
+        assert!(matches!(PublicKey::from_cose(&wrong_curve), Err(WebAuthnError::UnsupportedAlgorithm)));
+
+        let no_algorithm = encode(vec![(KEY_TYPE, integer(KEY_TYPE_EC2))]);
+        assert!(matches!(PublicKey::from_cose(&no_algorithm), Err(WebAuthnError::UnsupportedAlgorithm)));
+    }
+
+    #[test]
+    fn rejects_malformed_keys() {
+        let missing_y = encode(vec![
+            (KEY_TYPE, integer(KEY_TYPE_EC2)),
+            (ALGORITHM, integer(ES256 as i128)),
+            (EC2_CURVE, integer(CURVE_P256)),
+            (EC2_X, Value::Bytes(vec![0; 32])),
+        ]);
+        assert!(matches!(PublicKey::from_cose(&missing_y), Err(WebAuthnError::InvalidAuthenticatorData)));
+        assert!(matches!(PublicKey::from_cose(&[0xff, 0x00]), Err(WebAuthnError::InvalidAuthenticatorData)));
+
+        let mut not_a_map = Vec::new();
+        ciborium::into_writer(&Value::Array(vec![]), &mut not_a_map).unwrap();
+        assert!(matches!(PublicKey::from_cose(&not_a_map), Err(WebAuthnError::InvalidAuthenticatorData)));
+    }
+}
@@ -0,0 +1,177 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::app::context::Context;
+use crate::app::db::webauthn_credential_repository::{PasskeyDataset, WebAuthnCredentialRepository};
+use crate::app::models::Identity;
+use super::authenticator_data::{self, AuthenticatorData};
+use super::public_key::PublicKey;
+use super::{CEREMONY_TIMEOUT, Passkey, RelyingParty, SUPPORTED_ALGORITHMS, WebAuthnError, client_data, decode};
+
+/// `PublicKeyCredentialCreationOptions` handed to `navigator.credentials.create()`.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreationOptions {
+    challenge: String,
+    rp: RpEntity,
+    user: UserEntity,
+    pub_key_cred_params: Vec<CredentialParameter>,
+    timeout: u32,
+    exclude_credentials: Vec<CredentialDescriptor>,
+    authenticator_selection: AuthenticatorSelection,
+    attestation: &'static str,
+}
+
+#[derive(Serialize)]
+struct RpEntity {
+    id: String,
+    name: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UserEntity {
+    id: String,
+    name: String,
+    display_name: String,
+}
+
+#[derive(Serialize)]
+struct CredentialParameter {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    alg: i32,
+}
+
+#[derive(Serialize)]
+pub(super) struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub(super) kind: &'static str,
+    pub(super) id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AuthenticatorSelection {
+    resident_key: &'static str,
+    user_verification: &'static str,
+}
+
+pub struct RegistrationOptions<'a> {
+    context: &'a Context,
+    identity: &'a Identity,
+    challenge: String,
+}
+
+impl<'a> RegistrationOptions<'a> {
+    pub fn new(context: &'a Context, identity: &'a Identity, challenge: &str) -> Self {
+        Self {
+            context,
+            identity,
+            challenge: challenge.to_owned(),
+        }
+    }
+
+    pub async fn execute(self) -> Result<CreationOptions, WebAuthnError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = WebAuthnCredentialRepository::new(&connection);
+        let exclude_credentials = repository.list(&self.identity.id).await?
+            .into_iter()
+            .map(|passkey| CredentialDescriptor { kind: "public-key", id: passkey.credential_id })
+            .collect();
+
+        let rp = RelyingParty::from_config(&self.context.config);
+        let login = self.identity.login.clone().unwrap_or_else(|| self.identity.id.clone());
+        let options = CreationOptions {
+            challenge: self.challenge,
+            rp: RpEntity {
+                id: rp.id,
+                name: rp.name,
+            },
+            user: UserEntity {
+                id: URL_SAFE_NO_PAD.encode(self.identity.id.as_bytes()),
+                display_name: self.identity.name.clone().unwrap_or_else(|| login.clone()),
+                name: login,
+            },
+            pub_key_cred_params: SUPPORTED_ALGORITHMS.iter()
+                .map(|alg| CredentialParameter { kind: "public-key", alg: *alg })
+                .collect(),
+            timeout: CEREMONY_TIMEOUT,
+            exclude_credentials,
+            authenticator_selection: AuthenticatorSelection {
+                resident_key: "preferred",
+                user_verification: "preferred",
+            },
+            attestation: "none",
+        };
+        Ok(options)
+    }
+}
+
+/// The `PublicKeyCredential` returned by `navigator.credentials.create()`,
+/// with binary fields encoded as base64url.
+#[derive(Deserialize)]
+pub struct PasskeyAttestation {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Deserialize)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+pub struct PasskeyRegistration<'a> {
+    context: &'a Context,
+    identity_id: String,
+    challenge: Option<String>,
+    attestation: PasskeyAttestation,
+    name: String,
+}
+
+impl<'a> PasskeyRegistration<'a> {
+    pub fn new(context: &'a Context, identity_id: &str, challenge: Option<String>, attestation: PasskeyAttestation, name: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+            challenge,
+            attestation,
+            name: name.to_owned(),
+        }
+    }
+
+    pub async fn execute(self) -> Result<Passkey, WebAuthnError> {
+        let challenge = self.challenge.ok_or(WebAuthnError::ChallengeMissing)?;
+        let rp = RelyingParty::from_config(&self.context.config);
+        let response = &self.attestation.response;
+
+        let client_data_json = decode(&response.client_data_json, WebAuthnError::InvalidClientData)?;
+        client_data::verify(&client_data_json, client_data::CREATE, &challenge, &rp)?;
+
+        let attestation_object = decode(&response.attestation_object, WebAuthnError::InvalidAttestation)?;
+        let auth_data = authenticator_data::parse_attestation_object(&attestation_object)?;
+        let auth_data = AuthenticatorData::parse(&auth_data, &rp)?;
+        let credential = auth_data.attested_credential.ok_or(WebAuthnError::InvalidAuthenticatorData)?;
+        let credential_id = URL_SAFE_NO_PAD.encode(&credential.credential_id);
+        if credential_id != self.attestation.id {
+            return Err(WebAuthnError::InvalidAuthenticatorData)
+        }
+        let public_key = PublicKey::from_cose(&credential.public_key)?;
+
+        let connection = self.context.db.establish_connection().await?;
+        let repository = WebAuthnCredentialRepository::new(&connection);
+        let dataset = PasskeyDataset {
+            credential_id,
+            public_key: credential.public_key,
+            algorithm: public_key.algorithm(),
+            sign_count: auth_data.sign_count as i64,
+            name: self.name,
+        };
+        let passkey = repository.create(&self.identity_id, &dataset).await?
+            .ok_or(WebAuthnError::CredentialAlreadyRegistered)?;
+        Ok(passkey)
+    }
+}
@@ -0,0 +1,27 @@
+use crate::app::context::Context;
+use crate::app::db::webauthn_credential_repository::WebAuthnCredentialRepository;
+use crate::app::models::DomainError;
+use super::Passkey;
+
+pub struct PasskeyRemoval<'a> {
+    context: &'a Context,
+    identity_id: String,
+    id: String,
+}
+
+impl<'a> PasskeyRemoval<'a> {
+    pub fn new(context: &'a Context, identity_id: &str, id: &str) -> Self {
+        Self {
+            context,
+            identity_id: identity_id.to_owned(),
+            id: id.to_owned(),
+        }
+    }
+
+    pub async fn execute(&self) -> Result<Passkey, DomainError> {
+        let connection = self.context.db.establish_connection().await?;
+        let repository = WebAuthnCredentialRepository::new(&connection);
+        let passkey = repository.delete(&self.identity_id, &self.id).await?;
+        Ok(passkey)
+    }
+}
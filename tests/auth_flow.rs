@@ -0,0 +1,1675 @@
+use std::net::TcpListener;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_session::SessionMiddleware;
+use actix_web::cookie::{Cookie, Key};
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::{StatusCode, header};
+use actix_web::web::{Data, Form, get, post};
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, test};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use ciborium::Value as CborValue;
+use serde_derive::Deserialize;
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::Url;
+use ring::rand::SystemRandom;
+use ring::signature::{ECDSA_P256_SHA256_ASN1_SIGNING, EcdsaKeyPair, KeyPair};
+use sha2::{Digest, Sha256};
+use serde_json::{Value, json};
+
+use actixexp::app::config::ApplicationConfig;
+use actixexp::app::context::Context;
+use actixexp::app::db::session_store::PostgresSessionStore;
+use actixexp::app::handlers;
+use actixexp::app::middlewares::{CSRF_HEADER, SessionKeyRotation};
+use totp_rs::{Algorithm as TotpAlgorithm, Secret, TOTP};
+
+const DATABASE_URL_VARIABLE: &str = "ACTIXEXP_TEST_DATABASE_URL";
+const MOCK_CODE: &str = "mock-code";
+const MOCK_ACCESS_TOKEN: &str = "mock-access-token";
+const MOCK_OIDC_CLIENT: &str = "mock-oidc-client";
+const MOCK_KEY_ID: &str = "mock-key";
+const MOCK_SIGNING_KEY: &str = include_str!("fixtures/oidc_signing_key.pem");
+const MOCK_SIGNING_KEY_MODULUS: &str = "nIKqLXohmwq3Dph6U06E1PYLDom4lkw_XOo50fS5qfvLAg3SdDWZDZ1GL0cdYGImkpeePKA8mRVq3lXmXWC_r4ZQG0Zbtr9w3cHHbpnlRw3adSSFKjgfqYoPDW_Q1JePjPIuHgPsiw_5ie9_vzoUUbP-Yfe39wzCbcNE0PT_F3S3mBZqE2N5Pu9tKhWyx9AWNNjEK78EnF3PsSdXQBi3MvJ5_IgfnOozRPHlox_UWOLdI4-QH1cEm62rjc8vh3PBUD9AKpx9QIyyBGFpceK1ts5IzI-6ORVWnKYM-amJ-KfUtvTXTMiKvWiA8BH-7FaawthfT9i3CxcdgVJKVyXeXw";
+
+struct MockIssuer(String);
+
+#[derive(Deserialize)]
+struct MockTokenParams {
+    code: String,
+    code_verifier: String,
+}
+
+async fn mock_token(params: Form<MockTokenParams>) -> HttpResponse {
+    if params.code != MOCK_CODE || params.code_verifier.len() < 43 {
+        return HttpResponse::BadRequest().json(json!({ "error": "bad_verification_code" }))
+    }
+    HttpResponse::Ok().json(json!({
+        "access_token": MOCK_ACCESS_TOKEN,
+        "token_type": "bearer",
+    }))
+}
+
+async fn mock_user(req: HttpRequest) -> HttpResponse {
+    let expected = format!("token {}", MOCK_ACCESS_TOKEN);
+    let authorization = req.headers().get("Authorization").and_then(|value| value.to_str().ok());
+    if authorization != Some(expected.as_str()) {
+        return HttpResponse::Unauthorized().finish()
+    }
+    HttpResponse::Ok().json(json!({
+        "id": 987654321,
+        "login": "mock-user",
+        "name": "Mock User",
+        "avatar_url": "https://example.com/avatar.png",
+    }))
+}
+
+async fn mock_openid_configuration(issuer: Data<MockIssuer>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "issuer": issuer.0,
+        "authorization_endpoint": format!("{}/oidc/authorize", issuer.0),
+        "token_endpoint": format!("{}/oidc/token", issuer.0),
+        "jwks_uri": format!("{}/oidc/jwks", issuer.0),
+    }))
+}
+
+async fn mock_jwks() -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "keys": [{
+            "kty": "RSA",
+            "kid": MOCK_KEY_ID,
+            "use": "sig",
+            "alg": "RS256",
+            "n": MOCK_SIGNING_KEY_MODULUS,
+            "e": "AQAB",
+        }],
+    }))
+}
+
+/// Issues an ID token whose nonce is the authorization code, so tests pick
+/// the nonce through the code they send to the callback. A code of the form
+/// `nonce:subject` also picks the subject.
+async fn mock_oidc_token(issuer: Data<MockIssuer>, params: Form<MockTokenParams>) -> HttpResponse {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let (nonce, subject) = params.code.split_once(':').unwrap_or((&params.code, "oidc-subject"));
+    let claims = json!({
+        "iss": issuer.0,
+        "aud": MOCK_OIDC_CLIENT,
+        "sub": subject,
+        "exp": now + 300,
+        "iat": now,
+        "nonce": nonce,
+        "preferred_username": "oidc-user",
+        "name": "OIDC User",
+    });
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(MOCK_KEY_ID.to_owned());
+    let key = EncodingKey::from_rsa_pem(MOCK_SIGNING_KEY.as_bytes()).unwrap();
+    let id_token = jsonwebtoken::encode(&header, &claims, &key).unwrap();
+    HttpResponse::Ok().json(json!({
+        "access_token": MOCK_ACCESS_TOKEN,
+        "token_type": "bearer",
+        "id_token": id_token,
+    }))
+}
+
+fn start_mock_provider() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let issuer = format!("http://{}", address);
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(Data::new(MockIssuer(issuer.clone())))
+            .route("/login/oauth/access_token", post().to(mock_token))
+            .route("/user", get().to(mock_user))
+            .route("/.well-known/openid-configuration", get().to(mock_openid_configuration))
+            .route("/oidc/jwks", get().to(mock_jwks))
+            .route("/oidc/token", post().to(mock_oidc_token))
+    })
+    .workers(1)
+    .listen(listener)
+    .unwrap()
+    .run();
+    actix_rt::spawn(server);
+    format!("http://{}", address)
+}
+
+/// Points the app at the mock provider and the test database. The tests
+/// that call this are `#[ignore]`d, so running them without a database is
+/// a mistake worth failing on.
+fn load_config(provider_uri: &str) -> ApplicationConfig {
+    let database_url = std::env::var(DATABASE_URL_VARIABLE)
+        .unwrap_or_else(|_| panic!("{} must point to a test database", DATABASE_URL_VARIABLE));
+    let database = tokio_postgres::Config::from_str(&database_url).unwrap();
+    let host = match &database.get_hosts()[0] {
+        tokio_postgres::config::Host::Tcp(host) => host.to_owned(),
+        _ => panic!("{} must point to a TCP host", DATABASE_URL_VARIABLE),
+    };
+    let password = database.get_password().map(|p| String::from_utf8_lossy(p).into_owned()).unwrap_or_default();
+
+    let content = format!(r#"
+        [server]
+        bind = "127.0.0.1"
+        port = 0
+
+        [app]
+        session_key = ""
+
+        [auth.mock]
+        kind = "github"
+        client_id = "mock-client"
+        client_secret = "mock-secret"
+        authorize_url = "{provider}/login/oauth/authorize"
+        token_url = "{provider}/login/oauth/access_token"
+        user_url = "{provider}/user"
+
+        [auth.corp]
+        kind = "oidc"
+        client_id = "{oidc_client}"
+        client_secret = "mock-secret"
+        issuer = "{provider}"
+
+        [database]
+        host = "{host}"
+        port = {port}
+        database = "{database}"
+        user = "{user}"
+        password = "{password}"
+
+        [frontend]
+        base_uri = "http://localhost:3000"
+
+        [token]
+        signing_key = "c2lnbmluZy1rZXktZm9yLXRva2VuLW1vZGUtdGVzdHM="
+        access_ttl_seconds = 60
+        "#,
+        provider = provider_uri,
+        oidc_client = MOCK_OIDC_CLIENT,
+        host = host,
+        port = database.get_ports().first().copied().unwrap_or(5432),
+        database = database.get_dbname().unwrap_or("actixexp"),
+        user = database.get_user().unwrap_or("actixexp"),
+        password = password,
+    );
+    toml::from_str(&content).unwrap()
+}
+
+fn location(response: &ServiceResponse) -> String {
+    response.headers().get(header::LOCATION).unwrap().to_str().unwrap().to_owned()
+}
+
+fn session_cookie(response: &ServiceResponse) -> Option<Cookie<'static>> {
+    response.response().cookies()
+        .find(|cookie| cookie.name() == "id")
+        .map(|cookie| cookie.into_owned())
+}
+
+/// Builds the application the way `main` does, minus CORS and logging.
+macro_rules! init_app {
+    ($context:expr) => {
+        test::init_service(
+            App::new()
+                .wrap(SessionMiddleware::new(PostgresSessionStore::new($context.db.clone()), Key::generate()))
+                .app_data(Data::new($context.clone()))
+                .configure(handlers::app_config)
+        ).await
+    };
+}
+
+/// Fetches the session's CSRF token, returning it with the latest cookie.
+macro_rules! fetch_csrf_token {
+    ($app:expr, $cookie:expr) => {{
+        let request = test::TestRequest::get().uri("/auth/csrf").cookie($cookie.clone()).to_request();
+        let response = $app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let cookie = session_cookie(&response).unwrap_or($cookie);
+        let body: Value = test::read_body_json(response).await;
+        (cookie, body["csrf_token"].as_str().unwrap().to_owned())
+    }};
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn signs_in_through_mock_provider() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+
+    let request = test::TestRequest::post().uri("/auth/mock").to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).expect("session cookie should be issued");
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["provider"], "mock");
+    assert_eq!(body["client_id"], "mock-client");
+    assert_eq!(body["authorize_url"], format!("{}/login/oauth/authorize", provider_uri));
+    assert_eq!(body["code_challenge_method"], "S256");
+    assert_eq!(body["code_challenge"].as_str().map(str::len), Some(43));
+
+    let request = test::TestRequest::post().uri("/auth/unknown").to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let request = test::TestRequest::post()
+        .uri("/auth/mock/callback")
+        .cookie(cookie.clone())
+        .set_form([("state", "forged-state"), ("code", MOCK_CODE)])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = test::TestRequest::post().uri("/auth/mock").cookie(cookie).to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).expect("session cookie should be issued");
+    let body: Value = test::read_body_json(response).await;
+    let state = body["state"].as_str().unwrap().to_owned();
+
+    let request = test::TestRequest::post()
+        .uri("/auth/mock/callback")
+        .cookie(cookie)
+        .set_form([("state", state.as_str()), ("code", MOCK_CODE)])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).expect("session should be renewed after sign-in");
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["name"], "Mock User");
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["login"], "mock-user");
+    assert_eq!(body["avatar_url"], "https://example.com/avatar.png");
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn signs_in_with_verified_id_token() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+
+    let request = test::TestRequest::post().uri("/auth/corp").to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).expect("session cookie should be issued");
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["authorize_url"], format!("{}/oidc/authorize", provider_uri));
+    let state = body["state"].as_str().unwrap().to_owned();
+
+    let request = test::TestRequest::post()
+        .uri("/auth/corp/callback")
+        .cookie(cookie.clone())
+        .set_form([("state", state.as_str()), ("code", "replayed-nonce")])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = test::TestRequest::post().uri("/auth/corp").cookie(cookie).to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).expect("session cookie should be issued");
+    let body: Value = test::read_body_json(response).await;
+    let state = body["state"].as_str().unwrap().to_owned();
+    let nonce = body["nonce"].as_str().unwrap().to_owned();
+
+    let request = test::TestRequest::post()
+        .uri("/auth/corp/callback")
+        .cookie(cookie)
+        .set_form([("state", state.as_str()), ("code", nonce.as_str())])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).expect("session should be renewed after sign-in");
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["login"], "oidc-user");
+    assert_eq!(body["name"], "OIDC User");
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn signs_in_through_redirects() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+
+    let request = test::TestRequest::get().uri("/auth/mock/login?return_to=https://evil.example.com/").to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = test::TestRequest::get().uri("/auth/mock/login?return_to=/servants").to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FOUND);
+    let cookie = session_cookie(&response).expect("session cookie should be issued");
+    let authorize_url = Url::parse(&location(&response)).unwrap();
+    assert!(authorize_url.as_str().starts_with(&format!("{}/login/oauth/authorize?", provider_uri)));
+    let query: Vec<(String, String)> = authorize_url.query_pairs().into_owned().collect();
+    let param = |name: &str| query.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+    assert_eq!(param("client_id").as_deref(), Some("mock-client"));
+    assert_eq!(param("code_challenge_method").as_deref(), Some("S256"));
+    let state = param("state").unwrap();
+
+    let request = test::TestRequest::get()
+        .uri(&format!("/auth/mock/callback?state={}&code={}", state, MOCK_CODE))
+        .cookie(cookie)
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(location(&response), "http://localhost:3000/servants");
+    let cookie = session_cookie(&response).expect("session should be renewed after sign-in");
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::get().uri("/auth/mock/login?return_to=/servants").to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let request = test::TestRequest::get()
+        .uri(&format!("/auth/mock/callback?state=forged&code={}", MOCK_CODE))
+        .cookie(cookie)
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(location(&response), "http://localhost:3000/servants?error=invalid_state");
+
+    let request = test::TestRequest::get().uri("/auth/mock/login").to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let request = test::TestRequest::get()
+        .uri("/auth/mock/callback?error=access_denied&state=whatever")
+        .cookie(cookie)
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(location(&response), "http://localhost:3000/?error=access_denied");
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn authenticates_with_personal_access_token() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+
+    let request = test::TestRequest::post().uri("/auth/mock").to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let request = test::TestRequest::post()
+        .uri("/auth/mock/callback")
+        .cookie(cookie)
+        .set_form([("state", body["state"].as_str().unwrap()), ("code", MOCK_CODE)])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+
+    let request = test::TestRequest::post()
+        .uri("/tokens")
+        .cookie(cookie.clone())
+        .set_json(json!({ "name": "forged", "scopes": ["servants:read"] }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN, "unsafe requests need the CSRF token");
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["error"], "invalid csrf token");
+
+
+    let request = test::TestRequest::post()
+        .uri("/tokens")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "name": "ci", "scopes": ["servants:read", "servants:write"], "expires_in_days": 30 }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(response).await;
+    let token_id = body["id"].as_str().unwrap().to_owned();
+    let bearer = format!("Bearer {}", body["secret"].as_str().unwrap());
+    assert!(body["expires_at"].is_string());
+
+    let request = test::TestRequest::get().uri("/servants").insert_header((header::AUTHORIZATION, bearer.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::get().uri("/tokens").insert_header((header::AUTHORIZATION, bearer.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let request = test::TestRequest::post()
+        .uri("/tokens")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "name": "dashboard", "scopes": ["servants:read"] }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let read_only = format!("Bearer {}", body["secret"].as_str().unwrap());
+
+    let request = test::TestRequest::get().uri("/servants").insert_header((header::AUTHORIZATION, read_only.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::post()
+        .uri("/servants")
+        .insert_header((header::AUTHORIZATION, read_only.as_str()))
+        .set_json(json!({ "name": "Mash", "class_name": "shielder" }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["required_scope"], "servants:write");
+
+    for uri in ["/me", "/me/credentials", "/me/totp", "/sessions", "/auth/webauthn/credentials"] {
+        let request = test::TestRequest::get().uri(uri).insert_header((header::AUTHORIZATION, read_only.as_str())).to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} must not be readable with an access token", uri);
+    }
+    for uri in ["/me", "/sessions"] {
+        let request = test::TestRequest::delete().uri(uri).insert_header((header::AUTHORIZATION, read_only.as_str())).to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{} must not be deletable with an access token", uri);
+    }
+
+    let request = test::TestRequest::get().uri("/tokens").cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let listed = body["tokens"].as_array().unwrap().iter().find(|token| token["id"] == token_id.as_str()).unwrap();
+    assert!(listed["last_used_at"].is_string());
+    assert!(listed.get("secret").is_none());
+
+    let request = test::TestRequest::delete().uri(&format!("/tokens/{}", token_id)).cookie(cookie).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::get().uri("/servants").insert_header((header::AUTHORIZATION, bearer.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn restricts_admin_routes_to_admins() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+
+    let request = test::TestRequest::post().uri("/auth/mock").to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let request = test::TestRequest::post()
+        .uri("/auth/mock/callback")
+        .cookie(cookie)
+        .set_form([("state", body["state"].as_str().unwrap()), ("code", MOCK_CODE)])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let identity_id = body["identifier"].as_str().unwrap().to_owned();
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+
+    let request = test::TestRequest::get().uri("/admin/identities").cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["required_role"], "admin");
+
+    let request = test::TestRequest::post()
+        .uri("/servants")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "name": "Artoria", "class_name": "saber" }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let servant_id = body["id"].as_i64().unwrap();
+
+    let connection = context.db.establish_connection().await.unwrap();
+    connection.execute("update identities set role = 'admin' where id = $1::varchar::uuid", &[&identity_id]).await.unwrap();
+
+    let request = test::TestRequest::get().uri("/admin/identities").cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert!(body["identities"].as_array().unwrap().iter().any(|identity| identity["id"] == identity_id.as_str()));
+
+    let request = test::TestRequest::patch()
+        .uri(&format!("/admin/identities/{}", identity_id))
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({}))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+    let request = test::TestRequest::patch()
+        .uri("/admin/identities/not-a-uuid")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "alive": true }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let request = test::TestRequest::get().uri(&format!("/admin/servants/{}", servant_id)).cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::delete().uri(&format!("/admin/servants/{}", servant_id)).cookie(cookie.clone()).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    connection.execute("update identities set role = 'user' where id = $1::varchar::uuid", &[&identity_id]).await.unwrap();
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn links_and_unlinks_provider_credentials() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+
+    let request = test::TestRequest::post().uri("/auth/mock").to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let request = test::TestRequest::post()
+        .uri("/auth/mock/callback")
+        .cookie(cookie)
+        .set_form([("state", body["state"].as_str().unwrap()), ("code", MOCK_CODE)])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let identity_id = body["identifier"].as_str().unwrap().to_owned();
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+
+    let request = test::TestRequest::post().uri("/auth/corp/link").to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = test::TestRequest::post().uri("/auth/corp/link").cookie(cookie.clone()).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+    let body: Value = test::read_body_json(response).await;
+    let subject = format!("linked-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+    let code = format!("{}:{}", body["nonce"].as_str().unwrap(), subject);
+    let request = test::TestRequest::post()
+        .uri("/auth/corp/callback")
+        .cookie(cookie)
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_form([("state", body["state"].as_str().unwrap()), ("code", code.as_str())])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["identifier"], identity_id.as_str());
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+
+    let request = test::TestRequest::get().uri("/me/credentials").cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let credentials = body["credentials"].as_array().unwrap();
+    let credential_id = |identifier: &str| credentials.iter()
+        .find(|credential| credential["provider_identifier"] == identifier)
+        .map(|credential| credential["id"].as_str().unwrap().to_owned())
+        .unwrap();
+    let linked = credential_id(&format!("corp:{}", subject));
+    let original = credential_id("mock:987654321");
+
+    let request = test::TestRequest::delete().uri(&format!("/me/credentials/{}", linked)).cookie(cookie.clone()).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::delete().uri(&format!("/me/credentials/{}", original)).cookie(cookie).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+}
+
+/// A software P-256 authenticator standing in for the browser.
+struct MockAuthenticator {
+    key_pair: EcdsaKeyPair,
+    credential_id: Vec<u8>,
+    rng: SystemRandom,
+}
+
+impl MockAuthenticator {
+    const ORIGIN: &'static str = "http://localhost:3000";
+    const RP_ID: &'static str = "localhost";
+
+    fn new() -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng).unwrap();
+        let credential_id = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos().to_be_bytes().to_vec();
+        Self { key_pair, credential_id, rng }
+    }
+
+    fn credential_id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+
+    fn client_data(ceremony: &str, challenge: &str) -> Vec<u8> {
+        json!({ "type": ceremony, "challenge": challenge, "origin": Self::ORIGIN }).to_string().into_bytes()
+    }
+
+    fn authenticator_data(flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut data = Sha256::digest(Self::RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend(sign_count.to_be_bytes());
+        data
+    }
+
+    fn attestation(&self, challenge: &str) -> Value {
+        let point = self.key_pair.public_key().as_ref();
+        let cose_key = CborValue::Map(vec![
+            (1.into(), 2.into()),
+            (3.into(), (-7).into()),
+            ((-1).into(), 1.into()),
+            ((-2).into(), CborValue::Bytes(point[1..33].to_vec())),
+            ((-3).into(), CborValue::Bytes(point[33..].to_vec())),
+        ]);
+        let mut auth_data = Self::authenticator_data(0x41, 0);
+        auth_data.extend([0; 16]);
+        auth_data.extend((self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend(&self.credential_id);
+        ciborium::into_writer(&cose_key, &mut auth_data).unwrap();
+
+        let attestation_object = CborValue::Map(vec![
+            ("fmt".into(), "none".into()),
+            ("attStmt".into(), CborValue::Map(vec![])),
+            ("authData".into(), CborValue::Bytes(auth_data)),
+        ]);
+        let mut encoded = Vec::new();
+        ciborium::into_writer(&attestation_object, &mut encoded).unwrap();
+        json!({
+            "id": self.credential_id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(Self::client_data("webauthn.create", challenge)),
+                "attestationObject": URL_SAFE_NO_PAD.encode(encoded),
+            },
+        })
+    }
+
+    fn assertion(&self, challenge: &str, sign_count: u32, user_handle: &str) -> Value {
+        let client_data = Self::client_data("webauthn.get", challenge);
+        let auth_data = Self::authenticator_data(0x05, sign_count);
+        let mut message = auth_data.clone();
+        message.extend(Sha256::digest(&client_data));
+        let signature = self.key_pair.sign(&self.rng, &message).unwrap();
+        json!({
+            "id": self.credential_id(),
+            "type": "public-key",
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(auth_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+                "userHandle": user_handle,
+            },
+        })
+    }
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn registers_and_signs_in_with_passkey() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+
+    let request = test::TestRequest::post().uri("/auth/mock").to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let request = test::TestRequest::post()
+        .uri("/auth/mock/callback")
+        .cookie(cookie)
+        .set_form([("state", body["state"].as_str().unwrap()), ("code", MOCK_CODE)])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let identity_id = body["identifier"].as_str().unwrap().to_owned();
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+
+    let request = test::TestRequest::post().uri("/auth/webauthn/register/start").to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = test::TestRequest::post().uri("/auth/webauthn/register/start").cookie(cookie.clone()).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["publicKey"]["rp"]["id"], "localhost");
+    let user_handle = body["publicKey"]["user"]["id"].as_str().unwrap().to_owned();
+
+    let authenticator = MockAuthenticator::new();
+    let mut attestation = authenticator.attestation(body["publicKey"]["challenge"].as_str().unwrap());
+    attestation["name"] = json!("Test key");
+    let request = test::TestRequest::post()
+        .uri("/auth/webauthn/register/finish")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(&attestation)
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+    let body: Value = test::read_body_json(response).await;
+    let passkey_id = body["id"].as_str().unwrap().to_owned();
+
+    let request = test::TestRequest::post()
+        .uri("/auth/webauthn/register/finish")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(&attestation)
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let request = test::TestRequest::post().uri("/auth/webauthn/login/start").to_request();
+    let response = app.call(request).await.unwrap();
+    let login_cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let assertion = authenticator.assertion(body["publicKey"]["challenge"].as_str().unwrap(), 1, &user_handle);
+    let request = test::TestRequest::post()
+        .uri("/auth/webauthn/login/finish")
+        .cookie(login_cookie)
+        .set_json(&assertion)
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let login_cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["identifier"], identity_id.as_str());
+    let (login_cookie, login_csrf) = fetch_csrf_token!(app, login_cookie);
+
+    let request = test::TestRequest::get().uri("/me").cookie(login_cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["id"], identity_id.as_str());
+
+    let request = test::TestRequest::post().uri("/auth/webauthn/login/start").cookie(login_cookie.clone()).insert_header((CSRF_HEADER, login_csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    let login_cookie = session_cookie(&response).unwrap_or(login_cookie);
+    let body: Value = test::read_body_json(response).await;
+    let replayed = authenticator.assertion(body["publicKey"]["challenge"].as_str().unwrap(), 1, &user_handle);
+    let request = test::TestRequest::post()
+        .uri("/auth/webauthn/login/finish")
+        .cookie(login_cookie)
+        .insert_header((CSRF_HEADER, login_csrf.as_str()))
+        .set_json(&replayed)
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = test::TestRequest::delete().uri(&format!("/auth/webauthn/credentials/{}", passkey_id)).cookie(cookie).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// Signs in through the mock OpenID provider as `subject`, returning the
+/// callback response.
+macro_rules! sign_in_with_corp {
+    ($app:expr, $subject:expr) => {{
+        let request = test::TestRequest::post().uri("/auth/corp").to_request();
+        let response = $app.call(request).await.unwrap();
+        let cookie = session_cookie(&response).unwrap();
+        let body: Value = test::read_body_json(response).await;
+        let code = format!("{}:{}", body["nonce"].as_str().unwrap(), $subject);
+        let request = test::TestRequest::post()
+            .uri("/auth/corp/callback")
+            .cookie(cookie)
+            .set_form([("state", body["state"].as_str().unwrap()), ("code", code.as_str())])
+            .to_request();
+        $app.call(request).await.unwrap()
+    }};
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn requires_totp_after_enrolment() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let subject = format!("totp-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+
+    let response = sign_in_with_corp!(app, subject);
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["second_factor_required"], false);
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+
+    let request = test::TestRequest::post().uri("/me/totp").cookie(cookie.clone()).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert!(body["otpauth_uri"].as_str().unwrap().starts_with("otpauth://totp/"));
+    let secret = Secret::Encoded(body["secret"].as_str().unwrap().to_owned()).to_bytes().unwrap();
+    let totp = TOTP::new(TotpAlgorithm::SHA1, 6, 1, 30, secret, None, String::new()).unwrap();
+    let code = totp.generate_current().unwrap();
+
+    let request = test::TestRequest::post()
+        .uri("/me/totp/confirm")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "code": code }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let recovery_codes = body["recovery_codes"].as_array().unwrap();
+    assert_eq!(recovery_codes.len(), 10);
+    let recovery_code = recovery_codes[0].as_str().unwrap().to_owned();
+    let spare_recovery_code = recovery_codes[1].as_str().unwrap().to_owned();
+
+    let response = sign_in_with_corp!(app, subject);
+    let pending = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["second_factor_required"], true);
+    let (pending, pending_csrf) = fetch_csrf_token!(app, pending);
+
+    let request = test::TestRequest::get().uri("/me").cookie(pending.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["error"], "second factor required");
+
+    let request = test::TestRequest::post()
+        .uri("/auth/totp")
+        .cookie(pending.clone())
+        .insert_header((CSRF_HEADER, pending_csrf.as_str()))
+        .set_json(json!({ "code": code }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "a code must not be accepted twice");
+    let pending = session_cookie(&response).unwrap_or(pending);
+
+    let request = test::TestRequest::post()
+        .uri("/auth/totp")
+        .cookie(pending)
+        .insert_header((CSRF_HEADER, pending_csrf.as_str()))
+        .set_json(json!({ "code": recovery_code }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).unwrap();
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = sign_in_with_corp!(app, subject);
+    let pending = session_cookie(&response).unwrap();
+    let (pending, pending_csrf) = fetch_csrf_token!(app, pending);
+    let request = test::TestRequest::post()
+        .uri("/auth/totp")
+        .cookie(pending)
+        .insert_header((CSRF_HEADER, pending_csrf.as_str()))
+        .set_json(json!({ "code": recovery_code }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "recovery codes are single use");
+
+    let request = test::TestRequest::post()
+        .uri("/me/totp/recovery-codes")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "code": recovery_code }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "regenerating recovery codes needs a valid code");
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+
+    let request = test::TestRequest::delete()
+        .uri("/me/totp")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "code": recovery_code }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "disabling TOTP needs a valid code");
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+
+    let request = test::TestRequest::delete()
+        .uri("/me/totp")
+        .cookie(cookie)
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "code": spare_recovery_code }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn locks_second_factor_across_sessions() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let subject = format!("totp-lockout-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+
+    let response = sign_in_with_corp!(app, subject);
+    let cookie = session_cookie(&response).unwrap();
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+    let request = test::TestRequest::post().uri("/me/totp").cookie(cookie.clone()).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+    let body: Value = test::read_body_json(response).await;
+    let secret = Secret::Encoded(body["secret"].as_str().unwrap().to_owned()).to_bytes().unwrap();
+    let totp = TOTP::new(TotpAlgorithm::SHA1, 6, 1, 30, secret, None, String::new()).unwrap();
+    let request = test::TestRequest::post()
+        .uri("/me/totp/confirm")
+        .cookie(cookie)
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "code": totp.generate_current().unwrap() }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let recovery_code = body["recovery_codes"][0].as_str().unwrap().to_owned();
+
+    // Each pending sign-in allows five guesses; fresh sign-ins must not
+    // reset the identity's budget.
+    for _ in 0..2 {
+        let response = sign_in_with_corp!(app, subject);
+        let pending = session_cookie(&response).unwrap();
+        let (mut pending, pending_csrf) = fetch_csrf_token!(app, pending);
+        for attempt in 1..=5 {
+            let request = test::TestRequest::post()
+                .uri("/auth/totp")
+                .cookie(pending.clone())
+                .insert_header((CSRF_HEADER, pending_csrf.as_str()))
+                .set_json(json!({ "code": "nope-nope" }))
+                .to_request();
+            let response = app.call(request).await.unwrap();
+            let expected = match attempt {
+                5 => StatusCode::TOO_MANY_REQUESTS,
+                _ => StatusCode::UNAUTHORIZED,
+            };
+            assert_eq!(response.status(), expected);
+            pending = session_cookie(&response).unwrap_or(pending);
+        }
+    }
+
+    let response = sign_in_with_corp!(app, subject);
+    let pending = session_cookie(&response).unwrap();
+    let (pending, pending_csrf) = fetch_csrf_token!(app, pending);
+    let request = test::TestRequest::post()
+        .uri("/auth/totp")
+        .cookie(pending)
+        .insert_header((CSRF_HEADER, pending_csrf.as_str()))
+        .set_json(json!({ "code": recovery_code }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS, "a locked second factor refuses even valid codes");
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn issues_and_rotates_token_pairs() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+
+    let request = test::TestRequest::post().uri("/auth/mock").to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let request = test::TestRequest::post()
+        .uri("/auth/mock/callback?mode=token")
+        .cookie(cookie)
+        .set_form([("state", body["state"].as_str().unwrap()), ("code", MOCK_CODE)])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["token_type"], "Bearer");
+    assert_eq!(body["expires_in"], 60);
+    let access_token = body["access_token"].as_str().unwrap().to_owned();
+    let refresh_token = body["refresh_token"].as_str().unwrap().to_owned();
+
+    let request = test::TestRequest::get()
+        .uri("/me")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}", access_token)))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["login"], "mock-user");
+
+    let request = test::TestRequest::get()
+        .uri("/me")
+        .insert_header((header::AUTHORIZATION, format!("Bearer {}x", access_token)))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = test::TestRequest::post()
+        .uri("/auth/token/refresh")
+        .set_json(json!({ "refresh_token": refresh_token }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let rotated = body["refresh_token"].as_str().unwrap().to_owned();
+    assert_ne!(rotated, refresh_token);
+
+    let request = test::TestRequest::post()
+        .uri("/auth/token/refresh")
+        .set_json(json!({ "refresh_token": refresh_token }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "a rotated refresh token must not be accepted again");
+
+    let request = test::TestRequest::post()
+        .uri("/auth/token/refresh")
+        .set_json(json!({ "refresh_token": rotated }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "reuse must revoke the whole token family");
+
+    let request = test::TestRequest::post().uri("/auth/mock").to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let request = test::TestRequest::post()
+        .uri("/auth/mock/callback?mode=token")
+        .cookie(cookie)
+        .set_form([("state", body["state"].as_str().unwrap()), ("code", MOCK_CODE)])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let refresh_token = body["refresh_token"].as_str().unwrap().to_owned();
+
+    let request = test::TestRequest::post()
+        .uri("/auth/token/revoke")
+        .set_json(json!({ "refresh_token": refresh_token }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::post()
+        .uri("/auth/token/refresh")
+        .set_json(json!({ "refresh_token": refresh_token }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn expires_idle_sessions() {
+    let provider_uri = start_mock_provider();
+    let mut config = load_config(&provider_uri);
+    config.session.idle_timeout_seconds = 2;
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let subject = format!("idle-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+
+    let response = sign_in_with_corp!(app, subject);
+    let cookie = session_cookie(&response).unwrap();
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+
+    actix_rt::time::sleep(std::time::Duration::from_secs(3)).await;
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["error"], "session expired");
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["error"], "login required");
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn expires_sessions_outside_login_required_scopes() {
+    let provider_uri = start_mock_provider();
+    let mut config = load_config(&provider_uri);
+    config.session.idle_timeout_seconds = 2;
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let subject = format!("idle-passkey-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+
+    let response = sign_in_with_corp!(app, subject);
+    let cookie = session_cookie(&response).unwrap();
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+
+    actix_rt::time::sleep(std::time::Duration::from_secs(3)).await;
+
+    let request = test::TestRequest::post()
+        .uri("/auth/webauthn/register/start")
+        .cookie(cookie)
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["error"], "session expired");
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn accepts_sessions_sealed_with_retired_keys() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let retired = Key::generate();
+    let primary = Key::generate();
+    let retired_app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(PostgresSessionStore::new(context.db.clone()), retired.clone()))
+            .app_data(Data::new(context.clone()))
+            .configure(handlers::app_config)
+    ).await;
+    let rotating_app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(PostgresSessionStore::new(context.db.clone()), primary.clone()))
+            .wrap(SessionKeyRotation::new(vec![primary.clone(), retired], &config.session))
+            .app_data(Data::new(context.clone()))
+            .configure(handlers::app_config)
+    ).await;
+    let primary_app = test::init_service(
+        App::new()
+            .wrap(SessionMiddleware::new(PostgresSessionStore::new(context.db.clone()), primary))
+            .app_data(Data::new(context.clone()))
+            .configure(handlers::app_config)
+    ).await;
+    let subject = format!("rotation-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+
+    let response = sign_in_with_corp!(retired_app, subject);
+    let cookie = session_cookie(&response).unwrap();
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie.clone()).to_request();
+    let response = primary_app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    // Listing passkeys leaves the session untouched, so the re-issued cookie
+    // comes from the rotation rather than a session update.
+    let request = test::TestRequest::get().uri("/auth/webauthn/credentials").cookie(cookie).to_request();
+    let response = rotating_app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).expect("session should be re-issued under the primary key");
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie).to_request();
+    let response = primary_app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn requires_totp_after_passkey_sign_in() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let subject = format!("passkey-totp-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+
+    let response = sign_in_with_corp!(app, subject);
+    let cookie = session_cookie(&response).unwrap();
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+
+    let request = test::TestRequest::post().uri("/auth/webauthn/register/start").cookie(cookie.clone()).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+    let body: Value = test::read_body_json(response).await;
+    let user_handle = body["publicKey"]["user"]["id"].as_str().unwrap().to_owned();
+    let authenticator = MockAuthenticator::new();
+    let attestation = authenticator.attestation(body["publicKey"]["challenge"].as_str().unwrap());
+    let request = test::TestRequest::post()
+        .uri("/auth/webauthn/register/finish")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(&attestation)
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+
+    let request = test::TestRequest::post().uri("/me/totp").cookie(cookie.clone()).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+    let body: Value = test::read_body_json(response).await;
+    let secret = Secret::Encoded(body["secret"].as_str().unwrap().to_owned()).to_bytes().unwrap();
+    let totp = TOTP::new(TotpAlgorithm::SHA1, 6, 1, 30, secret, None, String::new()).unwrap();
+    let request = test::TestRequest::post()
+        .uri("/me/totp/confirm")
+        .cookie(cookie)
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "code": totp.generate_current().unwrap() }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let recovery_code = body["recovery_codes"][0].as_str().unwrap().to_owned();
+
+    let request = test::TestRequest::post().uri("/auth/webauthn/login/start").to_request();
+    let response = app.call(request).await.unwrap();
+    let login_cookie = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let assertion = authenticator.assertion(body["publicKey"]["challenge"].as_str().unwrap(), 1, &user_handle);
+    let request = test::TestRequest::post()
+        .uri("/auth/webauthn/login/finish")
+        .cookie(login_cookie)
+        .set_json(&assertion)
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let pending = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["second_factor_required"], true);
+
+    let request = test::TestRequest::get().uri("/me").cookie(pending.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let (pending, pending_csrf) = fetch_csrf_token!(app, pending);
+    let request = test::TestRequest::post()
+        .uri("/auth/totp")
+        .cookie(pending)
+        .insert_header((CSRF_HEADER, pending_csrf.as_str()))
+        .set_json(json!({ "code": recovery_code }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = session_cookie(&response).unwrap();
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn reports_pending_second_factor_through_redirects() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let subject = format!("redirect-totp-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+
+    let response = sign_in_with_corp!(app, subject);
+    let cookie = session_cookie(&response).unwrap();
+    let (cookie, csrf) = fetch_csrf_token!(app, cookie);
+    let request = test::TestRequest::post().uri("/me/totp").cookie(cookie.clone()).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap_or(cookie);
+    let body: Value = test::read_body_json(response).await;
+    let secret = Secret::Encoded(body["secret"].as_str().unwrap().to_owned()).to_bytes().unwrap();
+    let totp = TOTP::new(TotpAlgorithm::SHA1, 6, 1, 30, secret, None, String::new()).unwrap();
+    let request = test::TestRequest::post()
+        .uri("/me/totp/confirm")
+        .cookie(cookie)
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "code": totp.generate_current().unwrap() }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::get().uri("/auth/corp/login?return_to=/servants").to_request();
+    let response = app.call(request).await.unwrap();
+    let cookie = session_cookie(&response).unwrap();
+    let authorize_url = Url::parse(&location(&response)).unwrap();
+    let query: Vec<(String, String)> = authorize_url.query_pairs().into_owned().collect();
+    let param = |name: &str| query.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone()).unwrap();
+    let code = format!("{}:{}", param("nonce"), subject);
+    let callback_url = Url::parse_with_params("http://localhost/auth/corp/callback", [("state", param("state")), ("code", code)]).unwrap();
+
+    let request = test::TestRequest::get()
+        .uri(&format!("{}?{}", callback_url.path(), callback_url.query().unwrap()))
+        .cookie(cookie)
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(location(&response), "http://localhost:3000/servants?second_factor=required");
+    let pending = session_cookie(&response).unwrap();
+
+    let request = test::TestRequest::get().uri("/me").cookie(pending).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn hides_servants_from_other_owners() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let suffix = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+
+    let response = sign_in_with_corp!(app, format!("owner-{}", suffix));
+    let (owner, owner_csrf) = fetch_csrf_token!(app, session_cookie(&response).unwrap());
+    let response = sign_in_with_corp!(app, format!("stranger-{}", suffix));
+    let (stranger, stranger_csrf) = fetch_csrf_token!(app, session_cookie(&response).unwrap());
+
+    let request = test::TestRequest::post()
+        .uri("/servants")
+        .cookie(owner.clone())
+        .insert_header((CSRF_HEADER, owner_csrf.as_str()))
+        .set_json(json!({ "name": "Jeanne", "class_name": "ruler" }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(response).await;
+    let uri = format!("/servants/{}", body["id"].as_i64().unwrap());
+
+    let requests = [
+        test::TestRequest::get().uri(&uri),
+        test::TestRequest::patch().uri(&uri).set_json(json!({ "name": "Stolen" })),
+        test::TestRequest::put().uri(&uri).set_json(json!({ "name": "Stolen", "class_name": "avenger" })),
+        test::TestRequest::delete().uri(&uri),
+    ];
+    for request in requests {
+        let request = request.cookie(stranger.clone()).insert_header((CSRF_HEADER, stranger_csrf.as_str())).to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["error"], "not found");
+    }
+
+    let request = test::TestRequest::get().uri("/servants").cookie(stranger).to_request();
+    let response = app.call(request).await.unwrap();
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["servants"], json!([]));
+
+    let request = test::TestRequest::get().uri(&uri).cookie(owner.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["name"], "Jeanne");
+
+    let request = test::TestRequest::delete().uri(&uri).cookie(owner).insert_header((CSRF_HEADER, owner_csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn rejects_undecodable_bodies_with_field_errors() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let subject = format!("malformed-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+
+    let response = sign_in_with_corp!(app, subject);
+    let (cookie, csrf) = fetch_csrf_token!(app, session_cookie(&response).unwrap());
+
+    let bodies = [
+        ("{\"name\": \"Jeanne\",", "malformed"),
+        ("{\"name\": 5, \"class_name\": \"ruler\"}", "invalid_type"),
+    ];
+    for (body, code) in bodies {
+        let request = test::TestRequest::post()
+            .uri("/servants")
+            .cookie(cookie.clone())
+            .insert_header((CSRF_HEADER, csrf.as_str()))
+            .insert_header((header::CONTENT_TYPE, "application/json"))
+            .set_payload(body)
+            .to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["errors"][0]["field"], "body");
+        assert_eq!(body["errors"][0]["code"], code);
+    }
+
+    let request = test::TestRequest::get().uri("/auth/mock/callback?state=1").to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FOUND, "the redirect callback handles missing parameters itself");
+
+    let request = test::TestRequest::post()
+        .uri("/auth/mock/callback?mode=bogus")
+        .set_form([("state", "1"), ("code", MOCK_CODE)])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["errors"][0]["field"], "query");
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn replaces_and_patches_servants() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let subject = format!("updater-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+
+    let response = sign_in_with_corp!(app, subject);
+    let (cookie, csrf) = fetch_csrf_token!(app, session_cookie(&response).unwrap());
+
+    let request = test::TestRequest::post()
+        .uri("/servants")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "name": "Artoria", "class_name": "saber" }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(response).await;
+    let uri = format!("/servants/{}", body["id"].as_i64().unwrap());
+
+    let request = test::TestRequest::patch()
+        .uri(&uri)
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "name": "Altria" }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["name"], "Altria");
+    assert_eq!(body["class_name"], "saber", "PATCH keeps fields it was not given");
+
+    let request = test::TestRequest::put()
+        .uri(&uri)
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "name": "Altria Alter" }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY, "PUT replaces the whole servant");
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["errors"][0]["field"], "class_name");
+    assert_eq!(body["errors"][0]["code"], "required");
+
+    let request = test::TestRequest::put()
+        .uri(&uri)
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "name": "Altria Alter", "class_name": "lancer" }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["name"], "Altria Alter");
+    assert_eq!(body["class_name"], "lancer");
+
+    let request = test::TestRequest::patch()
+        .uri("/servants/2147483647")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "name": "Nobody" }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let request = test::TestRequest::delete().uri(&uri).cookie(cookie).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn deactivation_ends_every_sign_in() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let subject = format!("deactivated-{}", SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos());
+
+    let response = sign_in_with_corp!(app, subject);
+    let (cookie, csrf) = fetch_csrf_token!(app, session_cookie(&response).unwrap());
+    let response = sign_in_with_corp!(app, subject);
+    let other_session = session_cookie(&response).unwrap();
+
+    let request = test::TestRequest::post()
+        .uri("/tokens")
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .set_json(json!({ "name": "ci", "scopes": ["servants:read"] }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(response).await;
+    let personal_token = format!("Bearer {}", body["secret"].as_str().unwrap());
+
+    let request = test::TestRequest::post().uri("/auth/corp").to_request();
+    let response = app.call(request).await.unwrap();
+    let handshake = session_cookie(&response).unwrap();
+    let body: Value = test::read_body_json(response).await;
+    let code = format!("{}:{}", body["nonce"].as_str().unwrap(), subject);
+    let request = test::TestRequest::post()
+        .uri("/auth/corp/callback?mode=token")
+        .cookie(handshake)
+        .set_form([("state", body["state"].as_str().unwrap()), ("code", code.as_str())])
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let access_token = format!("Bearer {}", body["access_token"].as_str().unwrap());
+    let refresh_token = body["refresh_token"].as_str().unwrap().to_owned();
+
+    let request = test::TestRequest::delete().uri("/me").cookie(cookie).insert_header((CSRF_HEADER, csrf.as_str())).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::get().uri("/me").cookie(other_session.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["error"], "account deactivated");
+
+    let request = test::TestRequest::get().uri("/me").cookie(other_session).to_request();
+    let response = app.call(request).await.unwrap();
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["error"], "login required", "the other session is purged");
+
+    for bearer in [&personal_token, &access_token] {
+        let request = test::TestRequest::get().uri("/servants").insert_header((header::AUTHORIZATION, bearer.as_str())).to_request();
+        let response = app.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let body: Value = test::read_body_json(response).await;
+        assert_eq!(body["error"], "account deactivated");
+    }
+
+    let request = test::TestRequest::post()
+        .uri("/auth/token/refresh")
+        .set_json(json!({ "refresh_token": refresh_token }))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[actix_rt::test]
+#[ignore = "needs a database at ACTIXEXP_TEST_DATABASE_URL"]
+async fn lists_and_revokes_own_sessions_only() {
+    let provider_uri = start_mock_provider();
+    let config = load_config(&provider_uri);
+    let context = Context::initialize(&config).unwrap();
+    let app = init_app!(context);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_nanos();
+    let subject = format!("sessions-{}", nanos);
+    let stranger = format!("stranger-{}", nanos);
+
+    let response = sign_in_with_corp!(app, subject);
+    let (cookie, csrf) = fetch_csrf_token!(app, session_cookie(&response).unwrap());
+    let request = test::TestRequest::get().uri("/sessions").cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let sessions = body["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 1);
+    let current_id = sessions[0]["id"].as_str().unwrap().to_owned();
+
+    let response = sign_in_with_corp!(app, subject);
+    let other_session = session_cookie(&response).unwrap();
+    let request = test::TestRequest::get().uri("/sessions").cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let ids: Vec<&str> = body["sessions"].as_array().unwrap().iter().map(|session| session["id"].as_str().unwrap()).collect();
+    assert_eq!(ids.len(), 2);
+    assert!(ids.contains(&current_id.as_str()));
+    let other_id = ids.into_iter().find(|id| *id != current_id).unwrap().to_owned();
+
+    let response = sign_in_with_corp!(app, stranger);
+    let (stranger_cookie, stranger_csrf) = fetch_csrf_token!(app, session_cookie(&response).unwrap());
+    let request = test::TestRequest::delete()
+        .uri(&format!("/sessions/{}", current_id))
+        .cookie(stranger_cookie)
+        .insert_header((CSRF_HEADER, stranger_csrf.as_str()))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let request = test::TestRequest::get().uri("/me").cookie(cookie.clone()).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = test::TestRequest::delete()
+        .uri(&format!("/sessions/{}", other_id))
+        .cookie(cookie.clone())
+        .insert_header((CSRF_HEADER, csrf.as_str()))
+        .to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    assert_eq!(body["id"], other_id.as_str());
+
+    let request = test::TestRequest::get().uri("/me").cookie(other_session).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = test::TestRequest::get().uri("/sessions").cookie(cookie).to_request();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value = test::read_body_json(response).await;
+    let sessions = body["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0]["id"], current_id.as_str());
+}